@@ -81,9 +81,49 @@ pub fn solve<S: State + Clone + Debug>(start: S) -> Result<(u64, Vec<S>), HashSe
             };
             let priority = next_entry.priority();
 
-            queue.push_increase(next_entry, priority);
+            if queue.get_priority(&next_entry) < Some(&priority) {
+                queue.remove(&next_entry);
+                queue.push(next_entry, priority);
+            }
         }
     }
 
     Err(visited)
 }
+
+#[cfg(test)]
+mod test {
+    use super::{solve, State};
+
+    // A node in a small fixed graph, with no heuristic so that nodes are expanded in order of
+    // cost.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Node(char);
+
+    impl State for Node {
+        fn heuristic(&self) -> u64 {
+            0
+        }
+
+        fn successors(&self) -> Vec<(u64, Self)> {
+            match self.0 {
+                'S' => vec![(1, Node('A')), (5, Node('B'))],
+                'A' => vec![(1, Node('B'))],
+                'B' => vec![(1, Node('E'))],
+                _ => vec![],
+            }
+        }
+
+        fn is_end(&self) -> bool {
+            self.0 == 'E'
+        }
+    }
+
+    #[test]
+    fn test_cheaper_route_replaces_queued() {
+        // B is first queued straight from S at a cost of 5, then found through A at a cost of 2.
+        let (cost, route) = solve(Node('S')).unwrap();
+        assert_eq!(cost, 3);
+        assert_eq!(route, "SABE".chars().map(Node).collect::<Vec<_>>());
+    }
+}
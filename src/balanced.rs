@@ -0,0 +1,110 @@
+use failure::{err_msg, Error};
+use std::iter;
+
+/// The balanced bases that have symbols for their digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    Ternary,
+    Quinary,
+}
+
+impl Base {
+    fn radix(self) -> i64 {
+        match self {
+            Base::Ternary => 3,
+            Base::Quinary => 5,
+        }
+    }
+
+    // The symbols for each digit, from the most negative up.
+    fn symbols(self) -> &'static [char] {
+        match self {
+            Base::Ternary => &['-', '0', '1'],
+            Base::Quinary => &['=', '-', '0', '1', '2'],
+        }
+    }
+}
+
+/// Writes a value in a balanced base, where each digit ranges from `-(base / 2)` to `base / 2`.
+pub fn to_balanced(value: i64, base: Base) -> String {
+    let symbols = base.symbols();
+    let max_digit = base.radix() / 2;
+    let base = base.radix();
+
+    if value == 0 {
+        return symbols[max_digit as usize].to_string();
+    }
+
+    let mut value = value;
+    let digits = iter::from_fn(|| {
+        if value == 0 {
+            None
+        } else {
+            let mut d = value.rem_euclid(base);
+            if d > max_digit {
+                d -= base;
+            }
+            value -= d;
+            value /= base;
+            Some(symbols[(d + max_digit) as usize])
+        }
+    })
+    .collect::<Vec<_>>();
+
+    digits.iter().rev().collect()
+}
+
+pub fn from_balanced(s: &str, base: Base) -> Result<i64, Error> {
+    let symbols = base.symbols();
+    let max_digit = base.radix() / 2;
+    let base = base.radix();
+
+    s.chars().try_fold(0, |value, c| {
+        symbols
+            .iter()
+            .position(|&symbol| symbol == c)
+            .map(|index| value * base + index as i64 - max_digit)
+            .ok_or_else(|| err_msg(format!("Invalid digit {}", c)))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_balanced, to_balanced, Base};
+
+    #[test]
+    fn test_ternary() {
+        let cases = [
+            (0, "0"),
+            (1, "1"),
+            (2, "1-"),
+            (3, "10"),
+            (8, "10-"),
+            (-4, "--"),
+            (100, "11-01"),
+        ];
+        for (value, digits) in cases {
+            assert_eq!(to_balanced(value, Base::Ternary), digits);
+            assert_eq!(from_balanced(digits, Base::Ternary).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for base in [Base::Ternary, Base::Quinary] {
+            for value in -1000..=1000 {
+                assert_eq!(
+                    from_balanced(&to_balanced(value, base), base).unwrap(),
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(from_balanced("12", Base::Ternary).is_err());
+        assert!(from_balanced("1=", Base::Ternary).is_err());
+        assert!(from_balanced("1x", Base::Quinary).is_err());
+    }
+}
@@ -2,8 +2,10 @@
 
 use std::array;
 use std::cmp::{max, min, Ordering};
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::io::{self, Write};
 use std::ops::{Add, AddAssign, Div, Index, Mul, RangeInclusive, Sub};
 
 use failure::{err_msg, Error};
@@ -75,6 +77,67 @@ impl<T: PartialOrd, const S: usize> Vector<RangeInclusive<T>, S> {
     }
 }
 
+impl<const S: usize> Vector<RangeInclusive<i64>, S> {
+    pub fn around(position: &Vector<i64, S>) -> Self {
+        Vector(array::from_fn(|axis| position[axis]..=position[axis]))
+    }
+
+    pub fn extend(&self, position: &Vector<i64, S>) -> Self {
+        Vector(array::from_fn(|axis| {
+            min(*self[axis].start(), position[axis])..=max(*self[axis].end(), position[axis])
+        }))
+    }
+
+    pub fn bounding<'a>(positions: impl IntoIterator<Item = &'a Vector<i64, S>>) -> Option<Self> {
+        positions
+            .into_iter()
+            .fold(None, |bounds: Option<Self>, position| {
+                Some(match bounds {
+                    None => Self::around(position),
+                    Some(bounds) => bounds.extend(position),
+                })
+            })
+    }
+
+    pub fn expand(&self, amount: i64) -> Self {
+        Vector(array::from_fn(|axis| {
+            self[axis].start() - amount..=self[axis].end() + amount
+        }))
+    }
+
+    pub fn start(&self) -> Vector<i64, S> {
+        Vector(array::from_fn(|axis| *self[axis].start()))
+    }
+
+    /// The number of positions along each axis.
+    pub fn dimensions(&self) -> [i64; S] {
+        array::from_fn(|axis| self[axis].end() - self[axis].start() + 1)
+    }
+
+    pub fn volume(&self) -> usize {
+        self.dimensions()
+            .iter()
+            .map(|&size| size as usize)
+            .product()
+    }
+
+    pub fn surface_area(&self) -> usize {
+        let dimensions = self.dimensions().map(|size| size as usize);
+
+        // Each pair of opposite faces is the product of all the other dimensions.
+        (0..S)
+            .map(|axis| {
+                2 * dimensions
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != axis)
+                    .map(|(_, size)| size)
+                    .product::<usize>()
+            })
+            .sum()
+    }
+}
+
 impl<T: Debug, const S: usize> Debug for Vector<T, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{{")?;
@@ -141,42 +204,57 @@ impl<I: IntoIterator<Item = Position>> From<I> for Bounds {
     }
 }
 
+// The bounding box arithmetic is shared with the N-dimensional `Vector` ranges, with
+// `NonEmptyBounds` as the two dimensional view of them.
 impl NonEmptyBounds {
     pub fn width(&self) -> i64 {
-        1 + self.bottom_right.x - self.top_left.x
+        Vector::from(*self).dimensions()[0]
     }
 
     pub fn height(&self) -> i64 {
-        1 + self.bottom_right.y - self.top_left.y
+        Vector::from(*self).dimensions()[1]
     }
 
     fn extend(&self, other: Position) -> Self {
-        NonEmptyBounds {
-            top_left: Position {
-                x: min(self.top_left.x, other.x),
-                y: min(self.top_left.y, other.y),
-            },
-            bottom_right: Position {
-                x: max(self.bottom_right.x, other.x),
-                y: max(self.bottom_right.y, other.y),
-            },
-        }
+        Vector::from(*self).extend(&other.into()).into()
     }
 
-    pub fn iter_x(&self) -> impl Iterator<Item = i64> {
+    pub fn iter_x(&self) -> impl DoubleEndedIterator<Item = i64> {
         self.top_left.x..=self.bottom_right.x
     }
 
-    pub fn iter_y(&self) -> impl Iterator<Item = i64> {
+    pub fn iter_y(&self) -> impl DoubleEndedIterator<Item = i64> {
         self.top_left.y..=self.bottom_right.y
     }
 }
 
 impl From<Position> for NonEmptyBounds {
     fn from(position: Position) -> Self {
+        Vector::around(&position.into()).into()
+    }
+}
+
+impl From<NonEmptyBounds> for Vector<RangeInclusive<i64>, 2> {
+    fn from(bounds: NonEmptyBounds) -> Self {
+        Vector([
+            bounds.top_left.x..=bounds.bottom_right.x,
+            bounds.top_left.y..=bounds.bottom_right.y,
+        ])
+    }
+}
+
+impl From<Vector<RangeInclusive<i64>, 2>> for NonEmptyBounds {
+    fn from(ranges: Vector<RangeInclusive<i64>, 2>) -> Self {
+        let [x, y] = ranges.0;
         NonEmptyBounds {
-            top_left: position,
-            bottom_right: position,
+            top_left: Position {
+                x: *x.start(),
+                y: *y.start(),
+            },
+            bottom_right: Position {
+                x: *x.end(),
+                y: *y.end(),
+            },
         }
     }
 }
@@ -246,16 +324,7 @@ impl Position {
     }
 
     pub fn bounds(self, other: Position) -> NonEmptyBounds {
-        NonEmptyBounds {
-            top_left: Position {
-                x: min(self.x, other.x),
-                y: min(self.y, other.y),
-            },
-            bottom_right: Position {
-                x: max(self.x, other.x),
-                y: max(self.y, other.y),
-            },
-        }
+        NonEmptyBounds::from(self).extend(other)
     }
 
     pub fn rotate(self, rotation: Rotation) -> Position {
@@ -278,6 +347,12 @@ impl Position {
     }
 }
 
+impl From<Position> for Vector<i64, 2> {
+    fn from(position: Position) -> Self {
+        Vector([position.x, position.y])
+    }
+}
+
 impl From<(i64, i64)> for Position {
     fn from((x, y): (i64, i64)) -> Self {
         Position { x, y }
@@ -419,6 +494,26 @@ impl Rotation {
     }
 }
 
+pub fn flood_fill<T, I, F>(start: T, successors: F) -> HashSet<T>
+where
+    T: Hash + Eq + Clone,
+    I: IntoIterator<Item = T>,
+    F: Fn(&T) -> I,
+{
+    let mut to_check = vec![start.clone()];
+    let mut found = HashSet::from([start]);
+
+    while let Some(current) = to_check.pop() {
+        for next in successors(&current) {
+            if found.insert(next.clone()) {
+                to_check.push(next);
+            }
+        }
+    }
+
+    found
+}
+
 pub fn div_ceil(lhs: u64, rhs: u64) -> u64 {
     (lhs / rhs) + if lhs % rhs == 0 { 0 } else { 1 }
 }
@@ -433,3 +528,106 @@ pub fn int_sqrt(val: u64) -> Option<u64> {
     }
     None
 }
+
+/// Writes a binary PPM image, drawing each cell of a `width` by `height` grid as a `scale` by
+/// `scale` square of the colour returned by `colour`.
+pub fn write_ppm<W: Write, F: Fn(usize, usize) -> [u8; 3]>(
+    mut writer: W,
+    width: usize,
+    height: usize,
+    scale: usize,
+    colour: F,
+) -> io::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", width * scale, height * scale)?;
+
+    for y in 0..height {
+        let row = (0..width)
+            .flat_map(|x| colour(x, y).repeat(scale))
+            .collect::<Vec<_>>();
+        for _ in 0..scale {
+            writer.write_all(&row)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+pub fn lcm(a: i64, b: i64) -> i64 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        (a / gcd(a, b) * b).abs()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{flood_fill, gcd, lcm, Bounds, NonEmptyBounds, Position, Vector};
+    use std::ops::RangeInclusive;
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(7, 0), 7);
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(120, 25), 600);
+        assert_eq!(lcm(5, 5), 5);
+        assert_eq!(lcm(0, 3), 0);
+    }
+
+    #[test]
+    fn test_bounding() {
+        let positions: Vec<Vector<i64, 3>> =
+            vec![[1, 5, -2].into(), [3, 0, 4].into(), [2, 2, 2].into()];
+        let bounds = Vector::bounding(positions.iter()).unwrap();
+        assert_eq!(bounds, Vector::from([1..=3, 0..=5, -2..=4]));
+        assert_eq!(bounds.start(), [1, 0, -2].into());
+        assert_eq!(bounds.expand(1), Vector::from([0..=4, -1..=6, -3..=5]));
+        assert_eq!(bounds.volume(), 3 * 6 * 7);
+        assert_eq!(bounds.surface_area(), 2 * (3 * 6 + 3 * 7 + 6 * 7));
+
+        let empty: Vec<Vector<i64, 2>> = vec![];
+        assert_eq!(
+            Vector::<RangeInclusive<i64>, 2>::bounding(empty.iter()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_bounds_match_vector() {
+        let positions = [(1, 5), (3, 0), (-2, 2)].map(Position::from);
+        let bounds = *Bounds::from(positions).non_empty().unwrap();
+        let vectors = positions.map(Vector::from);
+        let ranges = Vector::bounding(vectors.iter()).unwrap();
+        assert_eq!(bounds, NonEmptyBounds::from(ranges.clone()));
+        assert_eq!(Vector::from(bounds), ranges);
+        assert_eq!((bounds.width(), bounds.height()), (6, 6));
+        assert_eq!(
+            positions[0].bounds(positions[2]),
+            NonEmptyBounds::from(Vector::from([-2..=1, 2..=5]))
+        );
+        assert_eq!(Bounds::from([]).width(), 0);
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let bounds = Vector::from([0..=4, 0..=4]);
+        let wall = |position: &Vector<i64, 2>| position[0] == 2;
+        let filled = flood_fill(Vector::from([0, 0]), |position| {
+            position
+                .adjacent()
+                .filter(|adjacent| bounds.contains(adjacent) && !wall(adjacent))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(filled.len(), 10);
+        assert!(filled.iter().all(|position| position[0] < 2));
+    }
+}
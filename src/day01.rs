@@ -1,23 +1,26 @@
 use failure::Error;
+use std::cmp::Reverse;
 
 pub struct Solver {}
 
 impl super::Solver for Solver {
-    type Problem = Box<[Box<[u32]>]>;
+    type Problem = Box<[Box<[i64]>]>;
 
     fn parse_input(data: String) -> Result<Self::Problem, Error> {
-        let (mut elves, last) = data.lines().map(|line| line.parse::<u32>().ok()).fold(
-            (vec![], vec![]),
-            |(mut elves, mut current), value| {
+        let (mut elves, last) = data
+            .lines()
+            .map(|line| line.trim().parse::<i64>().ok())
+            .fold((vec![], vec![]), |(mut elves, mut current), value| {
                 if let Some(calories) = value {
                     current.push(calories);
                     (elves, current)
                 } else {
-                    elves.push(current.into_boxed_slice());
+                    if !current.is_empty() {
+                        elves.push(current.into_boxed_slice());
+                    }
                     (elves, vec![])
                 }
-            },
-        );
+            });
 
         if !last.is_empty() {
             elves.push(last.into_boxed_slice());
@@ -27,15 +30,111 @@ impl super::Solver for Solver {
     }
 
     fn solve(elves: Self::Problem) -> (Option<String>, Option<String>) {
-        let mut elf_calories = elves
+        let elf_calories = elves
             .iter()
-            .map(|elf| elf.iter().sum::<u32>())
+            .map(|elf| elf.iter().sum::<i64>())
             .collect::<Vec<_>>();
-        elf_calories.sort_unstable_by(|a, b| a.cmp(b).reverse());
 
-        let part_one = elf_calories[0].to_string();
-        let part_two = elf_calories.iter().take(3).sum::<u32>().to_string();
+        let part_one = sum_top_n(&elf_calories, 1).to_string();
+        let part_two = sum_top_n(&elf_calories, 3).to_string();
 
         (Some(part_one), Some(part_two))
     }
 }
+
+pub fn sum_top_n(elf_calories: &[i64], n: usize) -> i64 {
+    let mut elf_calories = elf_calories.to_vec();
+    elf_calories.sort_unstable_by(|a, b| a.cmp(b).reverse());
+    elf_calories.iter().take(n).sum()
+}
+
+pub fn elf_with_most_calories(elves: &[Box<[i64]>]) -> Option<(usize, i64)> {
+    elves
+        .iter()
+        .map(|elf| elf.iter().sum::<i64>())
+        .enumerate()
+        .max_by_key(|&(index, calories)| (calories, Reverse(index)))
+}
+
+pub fn top_n_elves(elves: &[Box<[i64]>], n: usize) -> Vec<usize> {
+    let mut elf_calories = elves
+        .iter()
+        .map(|elf| elf.iter().sum::<i64>())
+        .enumerate()
+        .collect::<Vec<_>>();
+    elf_calories.sort_by_key(|&(index, calories)| (Reverse(calories), index));
+    elf_calories
+        .into_iter()
+        .take(n)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{elf_with_most_calories, sum_top_n, top_n_elves};
+    use crate::Solver;
+
+    const SAMPLE: &str = "1000
+2000
+3000
+
+4000
+
+5000
+6000
+
+7000
+8000
+9000
+
+10000
+";
+
+    #[test]
+    fn test_sum_top_n() {
+        let elves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let elf_calories = elves
+            .iter()
+            .map(|elf| elf.iter().sum::<i64>())
+            .collect::<Vec<_>>();
+        assert_eq!(sum_top_n(&elf_calories, 1), 24000);
+        assert_eq!(sum_top_n(&elf_calories, 3), 45000);
+    }
+
+    #[test]
+    fn test_elf_with_most_calories() {
+        let elves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(elf_with_most_calories(&elves), Some((3, 24000)));
+        assert_eq!(elf_with_most_calories(&[]), None);
+        assert_eq!(top_n_elves(&elves, 3), vec![3, 2, 4]);
+    }
+
+    #[test]
+    fn test_parse_crlf() {
+        let crlf = SAMPLE.replace('\n', "\r\n") + "\r\n";
+        let elves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let crlf_elves = super::Solver::parse_input(crlf).unwrap();
+        assert_eq!(elves, crlf_elves);
+        assert_eq!(crlf_elves.len(), 5);
+        assert_eq!(
+            super::Solver::solve(crlf_elves),
+            (Some("24000".to_string()), Some("45000".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_large_and_negative() {
+        let data = "5000000000\n\n4000000000\n1000000001\n\n-200\n100\n".to_string();
+        let elves = super::Solver::parse_input(data).unwrap();
+        assert_eq!(elf_with_most_calories(&elves), Some((1, 5000000001)));
+        assert_eq!(top_n_elves(&elves, 3), vec![1, 0, 2]);
+        assert_eq!(
+            super::Solver::solve(elves),
+            (
+                Some("5000000001".to_string()),
+                Some("9999999901".to_string())
+            )
+        );
+    }
+}
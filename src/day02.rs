@@ -2,8 +2,9 @@ use failure::{err_msg, Error};
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    combinator::{map, value},
-    multi::many1,
+    character::complete::newline,
+    combinator::{all_consuming, map, opt, value},
+    multi::separated_list1,
     sequence::{separated_pair, terminated},
     IResult,
 };
@@ -16,7 +17,7 @@ pub enum OpponentKey {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum PlayerKey {
+pub enum PlayerKey {
     X,
     Y,
     Z,
@@ -51,20 +52,21 @@ fn rule(input: &str) -> IResult<&str, Rule> {
 }
 
 fn rules(input: &str) -> IResult<&str, Box<[Rule]>> {
-    map(many1(terminated(rule, tag("\n"))), |rules| {
-        rules.into_boxed_slice()
-    })(input)
+    map(
+        terminated(separated_list1(newline, rule), opt(newline)),
+        Vec::into_boxed_slice,
+    )(input)
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum Hand {
+pub enum Hand {
     Rock,
     Paper,
     Scissors,
 }
 
 impl Hand {
-    fn score(self) -> u64 {
+    pub fn score(self) -> u64 {
         use Hand::*;
         match self {
             Rock => 1,
@@ -95,14 +97,14 @@ impl From<OpponentKey> for Hand {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum Outcome {
+pub enum Outcome {
     Win,
     Draw,
     Lose,
 }
 
 impl Outcome {
-    fn score(self) -> u64 {
+    pub fn score(self) -> u64 {
         use Outcome::*;
         match self {
             Win => 6,
@@ -122,7 +124,7 @@ impl From<PlayerKey> for Outcome {
     }
 }
 
-fn play_game(player: Hand, opponent: Hand) -> Outcome {
+pub fn play_game(player: Hand, opponent: Hand) -> Outcome {
     use Hand::*;
     use Outcome::*;
     match (player, opponent) {
@@ -138,7 +140,7 @@ fn play_game(player: Hand, opponent: Hand) -> Outcome {
     }
 }
 
-fn pick_hand(opponent: Hand, outcome: Outcome) -> Hand {
+pub fn pick_hand(opponent: Hand, outcome: Outcome) -> Hand {
     use Hand::*;
     use Outcome::*;
     match (opponent, outcome) {
@@ -154,13 +156,27 @@ fn pick_hand(opponent: Hand, outcome: Outcome) -> Hand {
     }
 }
 
+pub fn score_round_part_one(opponent: OpponentKey, player: PlayerKey) -> u64 {
+    let player: Hand = player.into();
+    let opponent: Hand = opponent.into();
+    let outcome = play_game(player, opponent);
+    player.score() + outcome.score()
+}
+
+pub fn score_round_part_two(opponent: OpponentKey, player: PlayerKey) -> u64 {
+    let opponent: Hand = opponent.into();
+    let outcome: Outcome = player.into();
+    let player = pick_hand(opponent, outcome);
+    player.score() + outcome.score()
+}
+
 pub struct Solver {}
 
 impl super::Solver for Solver {
     type Problem = Box<[Rule]>;
 
     fn parse_input(data: String) -> Result<Self::Problem, Error> {
-        rules(&data)
+        all_consuming(rules)(&data)
             .map(|(_, rules)| rules)
             .map_err(|err| err_msg(format!("Failed to parse rules: {}", err)))
     }
@@ -168,26 +184,57 @@ impl super::Solver for Solver {
     fn solve(problem: Self::Problem) -> (Option<String>, Option<String>) {
         let part_one = problem
             .iter()
-            .map(|rule| {
-                let player: Hand = rule.player.into();
-                let opponent: Hand = rule.opponent.into();
-                let outcome: Outcome = play_game(player, opponent);
-                player.score() + outcome.score()
-            })
+            .map(|rule| score_round_part_one(rule.opponent, rule.player))
             .sum::<u64>()
             .to_string();
 
         let part_two = problem
             .iter()
-            .map(|rule| {
-                let opponent: Hand = rule.opponent.into();
-                let outcome = rule.player.into();
-                let player = pick_hand(opponent, outcome);
-                player.score() + outcome.score()
-            })
+            .map(|rule| score_round_part_two(rule.opponent, rule.player))
             .sum::<u64>()
             .to_string();
 
         (Some(part_one), Some(part_two))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{score_round_part_one, score_round_part_two, OpponentKey, PlayerKey};
+    use crate::Solver;
+
+    const ROUNDS: [(OpponentKey, PlayerKey); 3] = [
+        (OpponentKey::A, PlayerKey::Y),
+        (OpponentKey::B, PlayerKey::X),
+        (OpponentKey::C, PlayerKey::Z),
+    ];
+
+    #[test]
+    fn test_score_round_part_one() {
+        let scores = ROUNDS.map(|(opponent, player)| score_round_part_one(opponent, player));
+        assert_eq!(scores, [8, 1, 6]);
+        assert_eq!(scores.iter().sum::<u64>(), 15);
+    }
+
+    #[test]
+    fn test_score_round_part_two() {
+        let scores = ROUNDS.map(|(opponent, player)| score_round_part_two(opponent, player));
+        assert_eq!(scores, [4, 1, 7]);
+        assert_eq!(scores.iter().sum::<u64>(), 12);
+    }
+
+    #[test]
+    fn test_no_trailing_newline() {
+        let rules = super::Solver::parse_input("A Y\nB X".to_string()).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            super::Solver::solve(rules),
+            (Some("9".to_string()), Some("5".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_unrecognized_token() {
+        assert!(super::Solver::parse_input("A Y\nA Q\nC Z\n".to_string()).is_err());
+    }
+}
@@ -1,8 +1,12 @@
-use failure::Error;
+use failure::{err_msg, Error};
 use itertools::Itertools;
-use std::{collections::HashSet, hash::Hash};
+use std::collections::HashSet;
 
-fn find_duplicate(contents: &[char]) -> Option<char> {
+use crate::report;
+
+const GROUP_SIZE: usize = 3;
+
+fn find_duplicate(contents: &[char]) -> Result<char, Error> {
     let first_compartment = contents[..contents.len() / 2]
         .iter()
         .cloned()
@@ -15,25 +19,56 @@ fn find_duplicate(contents: &[char]) -> Option<char> {
         .intersection(&second_compartment)
         .next()
         .cloned()
+        .ok_or_else(|| {
+            err_msg(format!(
+                "No common item in rucksack {}",
+                contents.iter().collect::<String>()
+            ))
+        })
 }
 
-fn score(item: char) -> u64 {
+fn score(item: char) -> Result<u64, Error> {
     match item {
-        'a'..='z' => 1 + (item as u64 - 'a' as u64),
-        'A'..='Z' => 27 + (item as u64 - 'A' as u64),
-        _ => panic!("Unknown item {}", item),
+        'a'..='z' => Ok(1 + (item as u64 - 'a' as u64)),
+        'A'..='Z' => Ok(27 + (item as u64 - 'A' as u64)),
+        _ => Err(err_msg(format!("Unknown item {:?}", item))),
     }
 }
 
-fn intersect_3<T: Eq + Hash + Clone>(a: HashSet<T>, b: HashSet<T>, c: HashSet<T>) -> HashSet<T> {
-    [a, b, c]
-        .into_iter()
+pub fn find_badge(group: &[&[char]]) -> Result<char, Error> {
+    let common = group
+        .iter()
+        .map(|contents| contents.iter().cloned().collect::<HashSet<_>>())
         .reduce(|x, y| x.intersection(&y).cloned().collect::<HashSet<_>>())
-        .unwrap()
+        .unwrap_or_default();
+    match common.into_iter().collect::<Vec<_>>()[..] {
+        [badge] => Ok(badge),
+        [] => Err(err_msg("No badge common to group")),
+        ref badges => Err(err_msg(format!(
+            "Ambiguous badge for group: {}",
+            badges.iter().sorted().collect::<String>()
+        ))),
+    }
 }
 
-fn pick_one<T>(set: HashSet<T>) -> Option<T> {
-    set.into_iter().next()
+fn group_badge(group: &[Box<[char]>]) -> Result<char, Error> {
+    let group = group.iter().map(|contents| &**contents).collect::<Vec<_>>();
+    find_badge(&group)
+}
+
+pub fn badges_with_groups(problem: &[Box<[char]>]) -> Result<Vec<(usize, char)>, Error> {
+    if problem.len() % GROUP_SIZE != 0 {
+        return Err(err_msg(format!(
+            "{} rucksacks can't be split into groups of {}",
+            problem.len(),
+            GROUP_SIZE
+        )));
+    }
+    problem
+        .chunks_exact(GROUP_SIZE)
+        .enumerate()
+        .map(|(index, group)| group_badge(group).map(|badge| (index, badge)))
+        .collect()
 }
 
 pub struct Solver {}
@@ -52,20 +87,88 @@ impl super::Solver for Solver {
     fn solve(problem: Self::Problem) -> (Option<String>, Option<String>) {
         let part_one = problem
             .iter()
-            .map(|contents| find_duplicate(contents).unwrap())
-            .map(score)
-            .sum::<u64>()
-            .to_string();
+            .map(|contents| find_duplicate(contents).and_then(score))
+            .sum::<Result<u64, Error>>();
 
-        let part_two = problem
-            .iter()
-            .map(|contents| contents.iter().cloned().collect::<HashSet<_>>())
-            .tuples()
-            .map(|(a, b, c)| intersect_3(a, b, c))
-            .map(|set| pick_one(set).unwrap())
-            .map(score)
-            .sum::<u64>()
-            .to_string();
-        (Some(part_one), Some(part_two))
+        let part_two = badges_with_groups(&problem).and_then(|badges| {
+            badges
+                .into_iter()
+                .map(|(_, badge)| score(badge))
+                .sum::<Result<u64, Error>>()
+        });
+
+        (report(1, part_one), report(2, part_two))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{badges_with_groups, find_badge, find_duplicate, score};
+    use crate::Solver;
+
+    const SAMPLE: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw
+";
+
+    #[test]
+    fn test_badges_with_groups() {
+        let rucksacks = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            badges_with_groups(&rucksacks).unwrap(),
+            vec![(0, 'r'), (1, 'Z')]
+        );
+        assert_eq!(
+            super::Solver::solve(rucksacks.clone()),
+            (Some("157".to_string()), Some("70".to_string()))
+        );
+
+        // A trailing partial group isn't scored.
+        let partial = rucksacks[..5].to_vec().into_boxed_slice();
+        assert!(badges_with_groups(&partial).is_err());
+        assert_eq!(super::Solver::solve(partial).1, None);
+    }
+
+    #[test]
+    fn test_unknown_item() {
+        assert_eq!(score('p').unwrap(), 16);
+        assert_eq!(score('L').unwrap(), 38);
+        assert!(score(' ').is_err());
+        assert!(score('1').is_err());
+
+        let rucksacks = super::Solver::parse_input("a  b\n".to_string()).unwrap();
+        assert_eq!(super::Solver::solve(rucksacks).0, None);
+    }
+
+    #[test]
+    fn test_no_duplicate() {
+        let contents = "abcdef".chars().collect::<Vec<_>>();
+        assert!(find_duplicate(&contents).is_err());
+
+        let contents = "abcdea".chars().collect::<Vec<_>>();
+        assert_eq!(find_duplicate(&contents).unwrap(), 'a');
+    }
+
+    #[test]
+    fn test_find_badge() {
+        let rucksacks =
+            ["abcX", "dXef", "gXhi", "jklX"].map(|contents| contents.chars().collect::<Vec<_>>());
+        let group = rucksacks.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        assert_eq!(find_badge(&group).unwrap(), 'X');
+        assert_eq!(find_badge(&group[..3]).unwrap(), 'X');
+
+        let rucksacks =
+            ["abX", "cdX", "efX", "ghi"].map(|contents| contents.chars().collect::<Vec<_>>());
+        let group = rucksacks.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        assert!(find_badge(&group).is_err());
+
+        let rucksacks =
+            ["abXY", "cYdX", "XeYf"].map(|contents| contents.chars().collect::<Vec<_>>());
+        let group = rucksacks.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        assert!(find_badge(&group).is_err());
+        assert!(find_badge(&[]).is_err());
     }
 }
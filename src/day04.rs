@@ -1,10 +1,12 @@
+use std::cmp::{max, min};
 use std::ops::RangeInclusive;
 
 use failure::{err_msg, Error};
+use itertools::Itertools;
 use nom::{
     bytes::complete::{tag, take_while1},
-    combinator::{map, map_res},
-    multi::many1,
+    combinator::{all_consuming, map, map_res},
+    multi::{many1, separated_list1},
     sequence::separated_pair,
     sequence::terminated,
     IResult,
@@ -20,7 +22,7 @@ fn number(input: &str) -> IResult<&str, u64> {
 
 fn range(input: &str) -> IResult<&str, RangeInclusive<u64>> {
     map(separated_pair(number, tag("-"), number), |(start, end)| {
-        start..=end
+        min(start, end)..=max(start, end)
     })(input)
 }
 
@@ -30,6 +32,16 @@ fn assignment(input: &str) -> IResult<&str, Assignment> {
     })(input)
 }
 
+fn range_list(input: &str) -> IResult<&str, Box<[RangeInclusive<u64>]>> {
+    map(separated_list1(tag(","), range), Vec::into_boxed_slice)(input)
+}
+
+pub fn parse_ranges(line: &str) -> Result<Box<[RangeInclusive<u64>]>, Error> {
+    all_consuming(range_list)(line.trim())
+        .map_err(|err| err_msg(format!("Failed to parse ranges: {}", err)))
+        .map(|(_, ranges)| ranges)
+}
+
 fn assignments(input: &str) -> IResult<&str, Box<[Assignment]>> {
     map(many1(terminated(assignment, tag("\n"))), |assignments| {
         assignments.into_boxed_slice()
@@ -41,27 +53,80 @@ pub struct Assignment {
     second: RangeInclusive<u64>,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Overlap {
+    None,
+    Partial,
+    Contained,
+}
+
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct OverlapStats {
+    pub num_contained: usize,
+    pub num_overlapping: usize,
+}
+
+impl OverlapStats {
+    pub fn from_assignments(assignments: &[Assignment]) -> Self {
+        assignments
+            .iter()
+            .fold(OverlapStats::default(), |mut stats, assignment| {
+                match assignment.classify() {
+                    Overlap::Contained => {
+                        stats.num_contained += 1;
+                        stats.num_overlapping += 1;
+                    }
+                    Overlap::Partial => stats.num_overlapping += 1,
+                    Overlap::None => {}
+                }
+                stats
+            })
+    }
+}
+
 impl Assignment {
+    pub fn classify(&self) -> Overlap {
+        if self.duplicate() {
+            Overlap::Contained
+        } else if self.overlaps() {
+            Overlap::Partial
+        } else {
+            Overlap::None
+        }
+    }
+
     fn duplicate(&self) -> bool {
         subset(&self.first, &self.second) || subset(&self.second, &self.first)
     }
 
     fn overlaps(&self) -> bool {
-        self.first.contains(self.second.start())
-            || self.first.contains(self.second.end())
-            || subset(&self.first, &self.second)
+        overlap(&self.first, &self.second)
     }
 }
 
-fn subset(first: &RangeInclusive<u64>, second: &RangeInclusive<u64>) -> bool {
-    (first.start() >= second.start()) && (first.end() <= second.end())
+fn overlap(first: &RangeInclusive<u64>, second: &RangeInclusive<u64>) -> bool {
+    first.start() <= second.end() && second.start() <= first.end()
 }
 
-fn count_if<T, F>(values: &[T], mut filter: F) -> usize
-where
-    F: FnMut(&T) -> bool,
-{
-    values.iter().filter(|v| filter(*v)).count()
+pub fn all_overlap(ranges: &[RangeInclusive<u64>]) -> bool {
+    match (
+        ranges.iter().map(|range| range.start()).max(),
+        ranges.iter().map(|range| range.end()).min(),
+    ) {
+        (Some(start), Some(end)) => start <= end,
+        _ => false,
+    }
+}
+
+pub fn any_pair_overlaps(ranges: &[RangeInclusive<u64>]) -> bool {
+    ranges
+        .iter()
+        .tuple_combinations()
+        .any(|(first, second)| overlap(first, second))
+}
+
+fn subset(first: &RangeInclusive<u64>, second: &RangeInclusive<u64>) -> bool {
+    (first.start() >= second.start()) && (first.end() <= second.end())
 }
 
 pub struct Solver {}
@@ -76,9 +141,72 @@ impl super::Solver for Solver {
     }
 
     fn solve(assignments: Self::Problem) -> (Option<String>, Option<String>) {
-        let part_one = count_if(&assignments, Assignment::duplicate).to_string();
-        let part_two = count_if(&assignments, Assignment::overlaps).to_string();
+        let stats = OverlapStats::from_assignments(&assignments);
+        let part_one = stats.num_contained.to_string();
+        let part_two = stats.num_overlapping.to_string();
 
         (Some(part_one), Some(part_two))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{all_overlap, any_pair_overlaps, parse_ranges, Overlap, OverlapStats};
+    use crate::Solver;
+
+    #[test]
+    fn test_overlap_stats() {
+        let data = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8
+"
+        .to_string();
+        let assignments = super::Solver::parse_input(data).unwrap();
+        assert_eq!(
+            assignments
+                .iter()
+                .map(|assignment| assignment.classify())
+                .collect::<Vec<_>>(),
+            vec![
+                Overlap::None,
+                Overlap::None,
+                Overlap::Partial,
+                Overlap::Contained,
+                Overlap::Contained,
+                Overlap::Partial
+            ]
+        );
+        assert_eq!(
+            OverlapStats::from_assignments(&assignments),
+            OverlapStats {
+                num_contained: 2,
+                num_overlapping: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_n_way_overlap() {
+        let ranges = parse_ranges("2-4,3-6,8-9").unwrap();
+        assert_eq!(ranges.len(), 3);
+        assert!(!all_overlap(&ranges));
+        assert!(any_pair_overlaps(&ranges));
+        assert!(all_overlap(&ranges[..2]));
+        assert!(!any_pair_overlaps(&ranges[1..]));
+
+        let ranges = parse_ranges("2-8,3-7,6-6").unwrap();
+        assert!(all_overlap(&ranges));
+    }
+
+    #[test]
+    fn test_reversed_range() {
+        let reversed = super::Solver::parse_input("8-2,3-4\n".to_string()).unwrap();
+        assert_eq!(reversed[0].first, 2..=8);
+        assert_eq!(reversed[0].classify(), Overlap::Contained);
+
+        assert_eq!(parse_ranges("6-4,1-3").unwrap()[0], 4..=6);
+    }
+}
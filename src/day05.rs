@@ -1,9 +1,11 @@
-use failure::Error;
+use crate::report;
+use failure::{err_msg, Error};
+use std::{iter::once, ops::RangeInclusive};
 pub struct Solver {}
 
 use nom::{
     bytes::complete::{tag, take_while1},
-    combinator::{map, map_res},
+    combinator::{all_consuming, map, map_res},
     sequence::tuple,
     IResult,
 };
@@ -40,18 +42,71 @@ struct Move {
     to: usize,
 }
 
+pub trait CrateMover {
+    fn rearrange(moved: Vec<char>) -> Vec<char>;
+}
+
+pub struct CrateMover9000;
+
+impl CrateMover for CrateMover9000 {
+    fn rearrange(mut moved: Vec<char>) -> Vec<char> {
+        moved.reverse();
+        moved
+    }
+}
+
+pub struct CrateMover9001;
+
+impl CrateMover for CrateMover9001 {
+    fn rearrange(moved: Vec<char>) -> Vec<char> {
+        moved
+    }
+}
+
 impl Move {
-    fn apply(&self, stacks: &mut [Vec<char>], multi: bool) {
-        let from = stacks[self.from - 1].len() - self.num_crates;
-        let mut moved = stacks[self.from - 1].drain(from..).collect::<Vec<_>>();
-        if !multi {
-            moved.reverse();
+    fn apply<M: CrateMover>(&self, stacks: &mut [Vec<char>]) -> Result<(), Error> {
+        for stack in [self.from, self.to] {
+            if stack == 0 || stack > stacks.len() {
+                return Err(err_msg(format!("No stack {}", stack)));
+            }
+        }
+
+        let available = stacks[self.from - 1].len();
+        let from = available.checked_sub(self.num_crates).ok_or_else(|| {
+            err_msg(format!(
+                "Can't move {} crates from stack {} which only has {}",
+                self.num_crates, self.from, available
+            ))
+        })?;
+        let moved = stacks[self.from - 1].drain(from..).collect::<Vec<_>>();
+        stacks[self.to - 1].extend(M::rearrange(moved));
+        Ok(())
+    }
+}
+
+fn label_columns(header: &[char]) -> Vec<RangeInclusive<usize>> {
+    let mut columns = vec![];
+    let mut start = None;
+
+    for (col, c) in header.iter().chain(once(&' ')).enumerate() {
+        match (c.is_ascii_digit(), start) {
+            (true, None) => start = Some(col),
+            (false, Some(first)) => {
+                columns.push(first..=col - 1);
+                start = None;
+            }
+            _ => {}
         }
-        stacks[self.to - 1].extend(moved);
     }
+
+    columns
+}
+
+fn is_crate(c: char) -> bool {
+    !matches!(c, ' ' | '[' | ']')
 }
 
-fn read_diagram<'a, T: Iterator<Item = &'a str>>(lines: T) -> Vec<Vec<char>> {
+fn read_diagram<'a, T: Iterator<Item = &'a str>>(lines: T) -> Result<Vec<Vec<char>>, Error> {
     let mut diagram_lines = vec![];
 
     for line in lines {
@@ -61,23 +116,35 @@ fn read_diagram<'a, T: Iterator<Item = &'a str>>(lines: T) -> Vec<Vec<char>> {
         diagram_lines.push(line.chars().collect::<Vec<_>>());
     }
 
-    let num_stacks = (diagram_lines[0].len() + 1) / 4;
-    let max_depth = diagram_lines.len() - 1;
+    let (header, rows) = diagram_lines
+        .split_last()
+        .ok_or_else(|| err_msg("Missing stack diagram"))?;
 
-    (0..num_stacks)
-        .map(|index| 1 + index * 4)
-        .map(|col| {
-            (0..=max_depth)
+    Ok(label_columns(header)
+        .into_iter()
+        .map(|cols| {
+            rows.iter()
                 .rev()
-                .map(|row| diagram_lines[row][col])
-                .take_while(|c| *c != ' ')
+                .map(|row| {
+                    cols.clone()
+                        .filter_map(|col| row.get(col).cloned())
+                        .find(|c| is_crate(*c))
+                })
+                .take_while(Option::is_some)
+                .flatten()
                 .collect()
         })
-        .collect()
+        .collect())
 }
 
-fn read_moves<'a, T: Iterator<Item = &'a str> + 'a>(lines: T) -> Vec<Move> {
-    lines.map(|line| crate_move(line).unwrap().1).collect()
+fn read_moves<'a, T: Iterator<Item = &'a str> + 'a>(lines: T) -> Result<Vec<Move>, Error> {
+    lines
+        .map(|line| {
+            all_consuming(crate_move)(line)
+                .map(|(_, crate_move)| crate_move)
+                .map_err(|err| err_msg(format!("Failed to parse move {:?}: {}", line, err)))
+        })
+        .collect()
 }
 
 pub struct Problem {
@@ -85,6 +152,16 @@ pub struct Problem {
     moves: Vec<Move>,
 }
 
+impl Problem {
+    pub fn rearrange<M: CrateMover>(&self) -> Result<Vec<Vec<char>>, Error> {
+        let mut stacks = self.stacks.clone();
+        for crate_move in &self.moves {
+            crate_move.apply::<M>(&mut stacks)?;
+        }
+        Ok(stacks)
+    }
+}
+
 fn top_of_stacks(stacks: &[Vec<char>]) -> String {
     stacks
         .iter()
@@ -97,27 +174,137 @@ impl super::Solver for Solver {
 
     fn parse_input(data: String) -> Result<Self::Problem, Error> {
         let mut lines = data.lines();
-        let stacks = read_diagram(&mut lines);
-        let moves = read_moves(&mut lines);
+        let stacks = read_diagram(&mut lines)?;
+        let moves = read_moves(&mut lines)?;
 
         Ok(Problem { stacks, moves })
     }
 
     fn solve(problem: Self::Problem) -> (Option<String>, Option<String>) {
-        let mut stacks = problem.stacks.clone();
-        for crate_move in &problem.moves {
-            crate_move.apply(&mut stacks, false);
-        }
+        let part_one = problem
+            .rearrange::<CrateMover9000>()
+            .map(|stacks| top_of_stacks(&stacks));
+        let part_two = problem
+            .rearrange::<CrateMover9001>()
+            .map(|stacks| top_of_stacks(&stacks));
+
+        (report(1, part_one), report(2, part_two))
+    }
+}
 
-        let part_one = top_of_stacks(&stacks);
+#[cfg(test)]
+mod test {
+    use super::{CrateMover, CrateMover9000, CrateMover9001};
+    use crate::Solver;
 
-        let mut stacks = problem.stacks.clone();
-        for crate_move in &problem.moves {
-            crate_move.apply(&mut stacks, true);
+    struct CrateMoverSorter;
+
+    impl CrateMover for CrateMoverSorter {
+        fn rearrange(mut moved: Vec<char>) -> Vec<char> {
+            moved.sort_unstable();
+            moved
         }
+    }
+
+    #[test]
+    fn test_sample() {
+        let data = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2
+"
+        .to_string();
+        let problem = super::Solver::parse_input(data).unwrap();
+        assert_eq!(
+            problem.stacks,
+            vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']]
+        );
+        assert_eq!(
+            super::Solver::solve(problem),
+            (Some("CMZ".to_string()), Some("MCD".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_ten_stacks() {
+        let data = "                  K
+A B C D E F G H I J
+1 2 3 4 5 6 7 8 9 10
+
+move 2 from 10 to 1
+"
+        .to_string();
+        let problem = super::Solver::parse_input(data).unwrap();
+        assert_eq!(problem.stacks.len(), 10);
+        assert_eq!(problem.stacks[9], vec!['J', 'K']);
+        assert_eq!(
+            super::Solver::solve(problem),
+            (
+                Some("JBCDEFGHI ".to_string()),
+                Some("KBCDEFGHI ".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_malformed_move() {
+        let data = " 1   2 
+
+move x from 1 to 2
+"
+        .to_string();
+        assert!(super::Solver::parse_input(data).is_err());
+    }
+
+    #[test]
+    fn test_custom_crate_mover() {
+        let data = "[C]    
+[A]    
+[B] [D]
+ 1   2 
+
+move 3 from 1 to 2
+"
+        .to_string();
+        let problem = super::Solver::parse_input(data).unwrap();
+        assert_eq!(
+            problem.rearrange::<CrateMoverSorter>().unwrap(),
+            vec![vec![], vec!['D', 'A', 'B', 'C']]
+        );
+    }
+
+    #[test]
+    fn test_too_many_crates() {
+        let data = "[A]    
+[B] [C]
+ 1   2 
+
+move 5 from 1 to 2
+"
+        .to_string();
+        let problem = super::Solver::parse_input(data).unwrap();
+        let err = problem.rearrange::<CrateMover9000>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't move 5 crates from stack 1 which only has 2"
+        );
+        assert_eq!(super::Solver::solve(problem), (None, None));
+    }
 
-        let part_two = top_of_stacks(&stacks);
+    #[test]
+    fn test_missing_stack() {
+        let data = "[A]
+ 1 
 
-        (Some(part_one), Some(part_two))
+move 1 from 1 to 3
+"
+        .to_string();
+        let problem = super::Solver::parse_input(data).unwrap();
+        assert!(problem.rearrange::<CrateMover9001>().is_err());
     }
 }
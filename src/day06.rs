@@ -1,8 +1,12 @@
-use failure::Error;
+use failure::{err_msg, Error};
+use itertools::Either;
+use std::{cmp::max, collections::HashMap};
 
-fn find_non_repeating<E: Eq>(values: &[E], len: usize) -> Option<usize> {
+use crate::report;
+
+fn marker_positions<E: Eq>(values: &[E], len: usize) -> impl Iterator<Item = usize> + '_ {
     let mut current_len = 0;
-    for (i, next) in values.iter().enumerate() {
+    values.iter().enumerate().filter_map(move |(i, next)| {
         let mut found_dup = false;
         for j in (i - current_len..i).rev() {
             if values[j] == *next {
@@ -15,12 +19,93 @@ fn find_non_repeating<E: Eq>(values: &[E], len: usize) -> Option<usize> {
             current_len += 1;
         }
 
-        if current_len == len {
-            return Some(i + 1);
+        if current_len >= len {
+            Some(i + 1)
+        } else {
+            None
+        }
+    })
+}
+
+pub fn find_non_repeating<E: Eq>(values: &[E], len: usize) -> Option<usize> {
+    marker_positions(values, len).next()
+}
+
+/// The length of the longest run of distinct lowercase letters ending at each position.
+fn lowercase_window_lengths(values: &[char]) -> impl Iterator<Item = usize> + '_ {
+    let mut last_seen = [-1i64; 26];
+    let mut start = 0;
+    values.iter().enumerate().map(move |(i, c)| {
+        let letter = (*c as u8 - b'a') as usize;
+        start = max(start, last_seen[letter] + 1);
+        last_seen[letter] = i as i64;
+        (i as i64 + 1 - start) as usize
+    })
+}
+
+/// The length of the longest run of distinct characters ending at each position.
+fn window_lengths(values: &[char]) -> impl Iterator<Item = usize> + '_ {
+    if values.iter().all(char::is_ascii_lowercase) {
+        Either::Left(lowercase_window_lengths(values))
+    } else {
+        let mut last_seen = HashMap::new();
+        let mut start = 0;
+        Either::Right(values.iter().enumerate().map(move |(i, c)| {
+            if let Some(last) = last_seen.insert(c, i) {
+                start = max(start, last + 1);
+            }
+            i + 1 - start
+        }))
+    }
+}
+
+fn lowercase_marker_positions(values: &[char], len: usize) -> impl Iterator<Item = usize> + '_ {
+    lowercase_window_lengths(values)
+        .enumerate()
+        .filter_map(move |(i, window)| if window >= len { Some(i + 1) } else { None })
+}
+
+fn char_marker_positions(values: &[char], len: usize) -> impl Iterator<Item = usize> + '_ {
+    if values.iter().all(char::is_ascii_lowercase) {
+        Either::Left(lowercase_marker_positions(values, len))
+    } else {
+        Either::Right(marker_positions(values, len))
+    }
+}
+
+pub fn find_marker(values: &[char], len: usize) -> Option<usize> {
+    char_marker_positions(values, len).next()
+}
+
+/// Finds the first marker of each of the given lengths in a single pass, keyed by length.
+/// Lengths which have no marker are left out.
+pub fn find_markers(values: &[char], lengths: &[usize]) -> HashMap<usize, usize> {
+    let mut markers = HashMap::new();
+
+    for (i, window) in window_lengths(values).enumerate() {
+        if markers.len() == lengths.len() {
+            break;
+        }
+
+        for &len in lengths {
+            if window >= len {
+                markers.entry(len).or_insert(i + 1);
+            }
         }
     }
 
-    None
+    markers
+}
+
+fn marker_end(markers: &HashMap<usize, usize>, len: usize) -> Result<usize, Error> {
+    markers
+        .get(&len)
+        .cloned()
+        .ok_or_else(|| err_msg(format!("No marker of length {} found", len)))
+}
+
+pub fn all_marker_positions(values: &[char], len: usize) -> Vec<usize> {
+    char_marker_positions(values, len).collect()
 }
 
 pub struct Solver {}
@@ -29,14 +114,111 @@ impl super::Solver for Solver {
     type Problem = String;
 
     fn parse_input(data: String) -> Result<Self::Problem, Error> {
-        Ok(data)
+        Ok(data.trim().to_string())
     }
 
     fn solve(data: Self::Problem) -> (Option<String>, Option<String>) {
         let chars = data.chars().collect::<Vec<_>>();
-        let part_one = find_non_repeating(&chars, 4).unwrap().to_string();
-        let part_two = find_non_repeating(&chars, 14).unwrap().to_string();
+        let markers = find_markers(&chars, &[4, 14]);
+        let part_one = marker_end(&markers, 4);
+        let part_two = marker_end(&markers, 14);
+
+        (report(1, part_one), report(2, part_two))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{all_marker_positions, find_marker, find_markers, find_non_repeating, marker_end};
+    use crate::Solver;
+    use std::collections::{HashMap, HashSet};
+
+    const SAMPLES: [(&str, usize, usize); 5] = [
+        ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+        ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+        ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+        ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+        ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+    ];
+
+    fn is_marker(chars: &[char], end: usize, len: usize) -> bool {
+        end >= len && chars[end - len..end].iter().collect::<HashSet<_>>().len() == len
+    }
+
+    #[test]
+    fn test_markers() {
+        for (data, packet, message) in SAMPLES {
+            let chars = data.chars().collect::<Vec<_>>();
+            assert_eq!(find_non_repeating(&chars, 4), Some(packet));
+            assert_eq!(find_non_repeating(&chars, 14), Some(message));
+
+            for (len, first) in [(4, packet), (14, message)] {
+                let expected = (1..=chars.len())
+                    .filter(|&end| is_marker(&chars, end, len))
+                    .collect::<Vec<_>>();
+                let positions = all_marker_positions(&chars, len);
+                assert_eq!(positions, expected);
+                assert_eq!(positions[0], first);
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_markers() {
+        for (data, packet, message) in SAMPLES {
+            let chars = data.chars().collect::<Vec<_>>();
+            assert_eq!(
+                find_markers(&chars, &[4, 14]),
+                HashMap::from([(4, packet), (14, message)])
+            );
+            assert_eq!(
+                find_markers(&chars, &[14, 1, 4]),
+                HashMap::from([(1, 1), (4, packet), (14, message)])
+            );
+
+            // Characters outside a-z take the general path.
+            let upper = data.to_uppercase().chars().collect::<Vec<_>>();
+            assert_eq!(
+                find_markers(&upper, &[4, 14]),
+                HashMap::from([(4, packet), (14, message)])
+            );
+        }
+    }
+
+    #[test]
+    fn test_fast_matches_slow() {
+        let mut seed = 12345u64;
+        let long = (0..10000)
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (b'a' + ((seed >> 33) % 16) as u8) as char
+            })
+            .collect::<String>();
+
+        for data in SAMPLES
+            .iter()
+            .map(|(data, _, _)| *data)
+            .chain([long.as_str()])
+        {
+            let chars = data.chars().collect::<Vec<_>>();
+            for len in [4, 14] {
+                assert_eq!(find_marker(&chars, len), find_non_repeating(&chars, len));
+                assert_eq!(
+                    find_markers(&chars, &[len]).get(&len).cloned(),
+                    find_non_repeating(&chars, len)
+                );
+            }
+        }
+    }
 
-        (Some(part_one), Some(part_two))
+    #[test]
+    fn test_no_marker() {
+        let chars = "aaaa".chars().collect::<Vec<_>>();
+        assert_eq!(find_marker(&chars, 4), None);
+        assert!(marker_end(&find_markers(&chars, &[4]), 4).is_err());
+        assert_eq!(find_markers(&chars, &[1, 4]), HashMap::from([(1, 1)]));
+        assert_eq!(super::Solver::solve("aaaa".to_string()), (None, None));
     }
 }
@@ -1,4 +1,5 @@
 use failure::{err_msg, Error};
+use itertools::Itertools;
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_while1},
@@ -100,15 +101,39 @@ fn commands(input: &str) -> IResult<&str, Box<[Command]>> {
     map(many1(command), Vec::into_boxed_slice)(input)
 }
 
-fn change_directory<'a, 'b: 'a>(cwd: &mut Vec<&'a str>, dirname: &'b str) {
-    if dirname == ".." {
-        cwd.pop();
-    } else if dirname == "/" {
-        cwd.clear()
-    } else if let Some(relative) = dirname.strip_prefix('/') {
-        *cwd = relative.split('/').collect();
-    } else {
-        cwd.extend(dirname.split('/'));
+#[derive(Debug, Default, PartialEq, Eq)]
+struct WorkingDirectory<'a>(Vec<&'a str>);
+
+impl<'a> WorkingDirectory<'a> {
+    /// Moves to the given directory, which is relative unless it starts with a '/'.
+    ///
+    /// As in a shell, going up from the root leaves us at the root.
+    fn change(&mut self, dirname: &'a str) {
+        let relative = match dirname.strip_prefix('/') {
+            Some(relative) => {
+                self.0.clear();
+                relative
+            }
+            None => dirname,
+        };
+
+        for component in relative.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => {
+                    if !self.0.is_empty() {
+                        self.0.pop();
+                    }
+                }
+                name => self.0.push(name),
+            }
+        }
+
+        debug_assert!(self.0.iter().all(|name| !name.is_empty()));
+    }
+
+    fn path(&self) -> &[&'a str] {
+        &self.0
     }
 }
 
@@ -127,17 +152,17 @@ fn get_directory_contents<'a>(
     contents
 }
 
-fn build_filesystem(commands: &[Command]) -> DirectoryEntry {
+pub fn build_filesystem(commands: &[Command]) -> DirectoryEntry {
     let mut root = DirectoryEntry::Directory(HashMap::new());
-    let mut cwd = vec![];
+    let mut cwd = WorkingDirectory::default();
 
     for command in commands {
         match command {
             Command::ChangeDirectory(dirname) => {
-                change_directory(&mut cwd, dirname);
+                cwd.change(dirname);
             }
             Command::ListDirectory(list_output) => {
-                let contents = get_directory_contents(&cwd, &mut root);
+                let contents = get_directory_contents(cwd.path(), &mut root);
                 for entry in list_output.iter() {
                     let (name, data) = match entry {
                         ListEntry::File(name, size) => (name.clone(), DirectoryEntry::File(*size)),
@@ -154,7 +179,28 @@ fn build_filesystem(commands: &[Command]) -> DirectoryEntry {
     root
 }
 
-fn get_directory_sizes(filesystem: &HashMap<String, DirectoryEntry>) -> DirectorySizeEntry {
+fn render_entry(output: &mut String, name: &str, entry: &DirectoryEntry, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match entry {
+        DirectoryEntry::Directory(contents) => {
+            output.push_str(&format!("{}- {} (dir)\n", indent, name));
+            for (name, child) in contents.iter().sorted_by_key(|(name, _)| *name) {
+                render_entry(output, name, child, depth + 1);
+            }
+        }
+        DirectoryEntry::File(size) => {
+            output.push_str(&format!("{}- {} (file, size={})\n", indent, name, size));
+        }
+    }
+}
+
+pub fn render_tree(root: &DirectoryEntry) -> String {
+    let mut output = String::new();
+    render_entry(&mut output, "/", root, 0);
+    output
+}
+
+pub fn get_directory_sizes(filesystem: &HashMap<String, DirectoryEntry>) -> DirectorySizeEntry {
     let children = filesystem
         .iter()
         .filter_map(|(name, entry)| {
@@ -180,6 +226,36 @@ fn get_directory_sizes(filesystem: &HashMap<String, DirectoryEntry>) -> Director
     DirectorySizeEntry { size, children }
 }
 
+pub fn directory_size(root: &DirectorySizeEntry, path: &str) -> Option<usize> {
+    path.split('/')
+        .filter(|name| !name.is_empty())
+        .try_fold(root, |directory, name| directory.children.get(name))
+        .map(|directory| directory.size)
+}
+
+/// The `n` largest files by their full path, biggest first.
+pub fn largest_files(root: &DirectoryEntry, n: usize) -> Vec<(String, usize)> {
+    let mut stack = vec![(String::new(), root)];
+    let mut files = vec![];
+
+    while let Some((path, entry)) = stack.pop() {
+        match entry {
+            DirectoryEntry::Directory(contents) => {
+                for (name, child) in contents.iter() {
+                    stack.push((format!("{}/{}", path, name), child));
+                }
+            }
+            DirectoryEntry::File(size) => files.push((path, *size)),
+        }
+    }
+
+    files.sort_by(|(path_a, size_a), (path_b, size_b)| {
+        size_b.cmp(size_a).then_with(|| path_a.cmp(path_b))
+    });
+    files.truncate(n);
+    files
+}
+
 fn find_directory_sizes<F>(dir_sizes: &DirectorySizeEntry, filter: F) -> Vec<usize>
 where
     F: Fn(&str, &DirectorySizeEntry) -> bool,
@@ -237,6 +313,56 @@ impl super::Solver for Solver {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::Solver;
+
+    const SAMPLE: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k
+";
+
+    #[test]
+    fn test_render_tree() {
+        let commands = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let filesystem = build_filesystem(&commands);
+        assert_eq!(
+            render_tree(&filesystem),
+            "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+"
+        );
+    }
 
     #[test]
     fn test_parse_ls() {
@@ -275,4 +401,96 @@ mod test {
             ))
         );
     }
+
+    #[test]
+    fn test_directory_size() {
+        let commands = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let filesystem = build_filesystem(&commands);
+        let dir_sizes = get_directory_sizes(filesystem.dir_contents().unwrap());
+        assert_eq!(directory_size(&dir_sizes, "/"), Some(48381165));
+        assert_eq!(directory_size(&dir_sizes, "/a"), Some(94853));
+        assert_eq!(directory_size(&dir_sizes, "/a/e"), Some(584));
+        assert_eq!(directory_size(&dir_sizes, "/d"), Some(24933642));
+        assert_eq!(directory_size(&dir_sizes, "/a/x"), None);
+    }
+
+    #[test]
+    fn test_largest_files() {
+        let commands = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let filesystem = build_filesystem(&commands);
+        assert_eq!(
+            largest_files(&filesystem, 3),
+            [
+                ("/b.txt", 14848514),
+                ("/c.dat", 8504156),
+                ("/d/d.log", 8033020)
+            ]
+            .map(|(path, size)| (path.to_string(), size))
+        );
+
+        let all = largest_files(&filesystem, 100);
+        assert_eq!(all.len(), 10);
+        assert_eq!(all[5], ("/d/j".to_string(), 4060174));
+        assert_eq!(all.last(), Some(&("/a/e/i".to_string(), 584)));
+        assert!(largest_files(&filesystem, 0).is_empty());
+    }
+
+    #[test]
+    fn test_cd_root() {
+        let mut cwd = WorkingDirectory::default();
+        cwd.change("a");
+        cwd.change("b");
+        cwd.change("/");
+        assert!(cwd.path().is_empty());
+    }
+
+    #[test]
+    fn test_cd_up_at_root() {
+        let mut cwd = WorkingDirectory::default();
+        cwd.change("..");
+        assert!(cwd.path().is_empty());
+        cwd.change("a");
+        assert_eq!(cwd.path(), &["a"]);
+    }
+
+    #[test]
+    fn test_build_cd_up_past_root() {
+        let data = "$ cd /
+$ cd ..
+$ cd ..
+$ ls
+dir a
+10 b
+$ cd a
+$ cd ../../a
+$ ls
+20 c
+";
+        let commands = super::Solver::parse_input(data.to_string()).unwrap();
+        let filesystem = build_filesystem(&commands);
+        assert_eq!(
+            render_tree(&filesystem),
+            "- / (dir)
+  - a (dir)
+    - c (file, size=20)
+  - b (file, size=10)
+"
+        );
+    }
+
+    #[test]
+    fn test_cd_nested() {
+        let mut cwd = WorkingDirectory::default();
+        cwd.change("/");
+        cwd.change("a");
+        cwd.change("e");
+        assert_eq!(cwd.path(), &["a", "e"]);
+        cwd.change("..");
+        cwd.change("f");
+        assert_eq!(cwd.path(), &["a", "f"]);
+        cwd.change("/b/c");
+        assert_eq!(cwd.path(), &["b", "c"]);
+        cwd.change("../d/");
+        assert_eq!(cwd.path(), &["b", "d"]);
+    }
 }
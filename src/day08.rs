@@ -9,18 +9,35 @@ pub struct HeightMap {
 }
 
 impl HeightMap {
-    fn new(heights: Box<[Box<[u32]>]>) -> Self {
-        let width = heights[0].len();
+    fn new(heights: Box<[Box<[u32]>]>) -> Result<Self, Error> {
+        let width = heights
+            .first()
+            .map(|row| row.len())
+            .ok_or_else(|| err_msg("No trees in the map"))?;
+        if let Some((y, row)) = heights
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.len() != width)
+        {
+            return Err(err_msg(format!(
+                "Row {} has {} trees, expected {}",
+                y,
+                row.len(),
+                width
+            )));
+        }
+
         let height = heights.len();
-        HeightMap {
+        Ok(HeightMap {
             heights,
             width,
             height,
-        }
+        })
     }
 
+    /// All positions in reading order.
     fn all_positions(&self) -> impl Iterator<Item = (usize, usize)> {
-        iproduct!(0..self.width, 0..self.height)
+        iproduct!(0..self.height, 0..self.width).map(|(y, x)| (x, y))
     }
 
     fn get_height(&self, (x, y): (usize, usize)) -> u32 {
@@ -52,10 +69,67 @@ impl HeightMap {
             .any(|(x2, y2)| self.heights[y2][x2] >= tree_height)
     }
 
-    fn is_tree_visible(&self, position: (usize, usize)) -> bool {
+    pub fn is_tree_visible(&self, position: (usize, usize)) -> bool {
         Direction::all().any(|direction| self.is_tree_visible_from_direction(position, direction))
     }
 
+    pub fn count_visible_fast(&self) -> usize {
+        let mut visible = vec![vec![false; self.width]; self.height];
+
+        let mut sweep = |positions: &mut dyn Iterator<Item = (usize, usize)>| {
+            let mut tallest = None;
+            for (x, y) in positions {
+                let tree_height = self.get_height((x, y));
+                if Some(tree_height) > tallest {
+                    visible[y][x] = true;
+                    tallest = Some(tree_height);
+                }
+            }
+        };
+
+        for y in 0..self.height {
+            sweep(&mut (0..self.width).map(|x| (x, y)));
+            sweep(&mut (0..self.width).rev().map(|x| (x, y)));
+        }
+
+        for x in 0..self.width {
+            sweep(&mut (0..self.height).map(|y| (x, y)));
+            sweep(&mut (0..self.height).rev().map(|y| (x, y)));
+        }
+
+        visible.iter().flatten().filter(|visible| **visible).count()
+    }
+
+    pub fn visibility_grid(&self) -> Vec<Vec<bool>> {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| self.is_tree_visible((x, y)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn scenic_grid(&self) -> Vec<Vec<usize>> {
+        (0..self.height)
+            .map(|y| (0..self.width).map(|x| self.scenic_score((x, y))).collect())
+            .collect()
+    }
+
+    /// Finds the position with the highest scenic score and that score, taking the first in
+    /// reading order (row by row) if several trees share it.
+    pub fn best_treehouse(&self) -> ((usize, usize), usize) {
+        self.all_positions()
+            .map(|position| (position, self.scenic_score(position)))
+            .fold(((0, 0), 0), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            })
+    }
+
     fn num_trees_visible_in_direction(
         &self,
         position: (usize, usize),
@@ -101,22 +175,14 @@ impl super::Solver for Solver {
             .map(parse_line)
             .collect::<Result<Vec<_>, _>>()
             .map(|rows| rows.into_boxed_slice())
-            .map(HeightMap::new)
+            .and_then(HeightMap::new)
     }
 
     fn solve(map: Self::Problem) -> (Option<String>, Option<String>) {
-        let part_one = map
-            .all_positions()
-            .filter(|&position| map.is_tree_visible(position))
-            .count()
-            .to_string();
+        let part_one = map.count_visible_fast().to_string();
 
-        let part_two = map
-            .all_positions()
-            .map(|position| map.scenic_score(position))
-            .max()
-            .unwrap()
-            .to_string();
+        let (_, best_score) = map.best_treehouse();
+        let part_two = best_score.to_string();
 
         (Some(part_one), Some(part_two))
     }
@@ -209,4 +275,76 @@ mod test {
             2
         );
     }
+
+    #[test]
+    fn test_count_visible_fast() {
+        let data = r"30373
+25512
+65332
+33549
+35390
+"
+        .to_string();
+        let map = super::Solver::parse_input(data).unwrap();
+        let slow = map
+            .all_positions()
+            .filter(|&position| map.is_tree_visible(position))
+            .count();
+        assert_eq!(slow, 21);
+        assert_eq!(map.count_visible_fast(), slow);
+    }
+
+    #[test]
+    fn test_grids() {
+        let data = r"30373
+25512
+65332
+33549
+35390
+"
+        .to_string();
+        let map = super::Solver::parse_input(data).unwrap();
+
+        let visibility = map.visibility_grid();
+        assert_eq!(visibility[1], vec![true, true, true, false, true]);
+        assert_eq!(visibility.iter().flatten().filter(|v| **v).count(), 21);
+
+        let scenic = map.scenic_grid();
+        assert_eq!(scenic[3][2], 8);
+        assert_eq!(scenic.iter().flatten().max(), Some(&8));
+    }
+
+    #[test]
+    fn test_best_treehouse() {
+        let data = r"30373
+25512
+65332
+33549
+35390
+"
+        .to_string();
+        let map = super::Solver::parse_input(data).unwrap();
+        assert_eq!(map.best_treehouse(), ((2, 3), 8));
+
+        // (2, 1) and (1, 2) both score 4, and (2, 1) comes first in reading order.
+        let data = "0000\n0010\n0100\n0000\n".to_string();
+        let map = super::Solver::parse_input(data).unwrap();
+        assert_eq!(map.scenic_score((1, 2)), 4);
+        assert_eq!(map.best_treehouse(), ((2, 1), 4));
+    }
+
+    #[test]
+    fn test_ragged() {
+        let data = r"30373
+25512
+6533
+33549
+35390
+"
+        .to_string();
+        let error = super::Solver::parse_input(data).err().unwrap();
+        assert_eq!(error.to_string(), "Row 2 has 4 trees, expected 5");
+
+        assert!(super::Solver::parse_input(String::new()).is_err());
+    }
 }
@@ -12,7 +12,13 @@ mod parse {
     };
 
     fn direction(input: &str) -> IResult<&str, Direction> {
+        // The diagonals have to be tried first so that their first letter isn't taken as a
+        // move on its own.
         alt((
+            value(Direction::UpLeft, tag("UL")),
+            value(Direction::UpRight, tag("UR")),
+            value(Direction::DownLeft, tag("DL")),
+            value(Direction::DownRight, tag("DR")),
             value(Direction::Up, tag("U")),
             value(Direction::Down, tag("D")),
             value(Direction::Left, tag("L")),
@@ -45,10 +51,10 @@ mod parse {
     }
 }
 
-use std::{cmp::Ordering, collections::HashSet};
+use std::{array, cmp::Ordering, collections::HashSet, iter::once};
 
-use crate::common::Position;
-use failure::Error;
+use crate::common::{Bounds, Position};
+use failure::{err_msg, Error};
 use itertools::{chain, repeat_n};
 use parse::parse_input;
 
@@ -58,6 +64,10 @@ enum Direction {
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -85,47 +95,74 @@ impl<const L: usize> Default for Rope<L> {
     }
 }
 
-impl<const L: usize> Rope<L> {
-    fn move_head(&mut self, direction: Direction) {
-        let head_position = &mut self.positions[0];
-        match direction {
-            Direction::Up => head_position.y += 1,
-            Direction::Down => head_position.y -= 1,
-            Direction::Left => head_position.x -= 1,
-            Direction::Right => head_position.x += 1,
-        }
-    }
+fn move_head(head_position: &mut Position, direction: Direction) {
+    let (dx, dy) = match direction {
+        Direction::Up => (0, 1),
+        Direction::Down => (0, -1),
+        Direction::Left => (-1, 0),
+        Direction::Right => (1, 0),
+        Direction::UpLeft => (-1, 1),
+        Direction::UpRight => (1, 1),
+        Direction::DownLeft => (-1, -1),
+        Direction::DownRight => (1, -1),
+    };
+    head_position.x += dx;
+    head_position.y += dy;
+}
 
-    fn move_tail(&mut self, index: usize) {
-        let last_position = self.positions[index - 1];
-        let tail_position = &mut self.positions[index];
+fn move_tail(last_position: Position, tail_position: &mut Position) {
+    if (tail_position.x - last_position.x).abs() < 2
+        && (tail_position.y - last_position.y).abs() < 2
+    {
+        return;
+    }
 
-        if (tail_position.x - last_position.x).abs() < 2
-            && (tail_position.y - last_position.y).abs() < 2
-        {
-            return;
-        }
+    tail_position.x += match last_position.x.cmp(&tail_position.x) {
+        Ordering::Greater => 1,
+        Ordering::Equal => 0,
+        Ordering::Less => -1,
+    };
 
-        tail_position.x += match last_position.x.cmp(&tail_position.x) {
-            Ordering::Greater => 1,
-            Ordering::Equal => 0,
-            Ordering::Less => -1,
-        };
+    tail_position.y += match last_position.y.cmp(&tail_position.y) {
+        Ordering::Greater => 1,
+        Ordering::Equal => 0,
+        Ordering::Less => -1,
+    };
+}
 
-        tail_position.y += match last_position.y.cmp(&tail_position.y) {
-            Ordering::Greater => 1,
-            Ordering::Equal => 0,
-            Ordering::Less => -1,
-        };
+fn move_rope(positions: &mut [Position], direction: Direction) {
+    move_head(&mut positions[0], direction);
+    for index in 1..positions.len() {
+        move_tail(positions[index - 1], &mut positions[index]);
     }
+}
 
+impl<const L: usize> Rope<L> {
     fn move_rope(&mut self, direction: Direction) {
-        self.move_head(direction);
-        for index in 1..L {
-            self.move_tail(index);
+        move_rope(&mut self.positions, direction);
+    }
+
+    fn tail_position(&self) -> Position {
+        *self.positions.last().unwrap()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct DynRope {
+    positions: Vec<Position>,
+}
+
+impl DynRope {
+    fn new(length: usize) -> Self {
+        DynRope {
+            positions: vec![Position::default(); length],
         }
     }
 
+    fn move_rope(&mut self, direction: Direction) {
+        move_rope(&mut self.positions, direction);
+    }
+
     fn tail_position(&self) -> Position {
         *self.positions.last().unwrap()
     }
@@ -135,19 +172,84 @@ fn expand(moves: &[Move]) -> impl Iterator<Item = Direction> + '_ {
     moves.iter().flat_map(|move_| move_.expand())
 }
 
-fn all_tail_positions<const L: usize>(moves: &[Move]) -> impl Iterator<Item = Position> + '_ {
+fn all_rope_states<const L: usize>(moves: &[Move]) -> impl Iterator<Item = Rope<L>> + '_ {
     let rope = Rope::<L>::default();
     chain(
+        [rope.clone()],
+        expand(moves).scan(rope, |rope, direction| {
+            rope.move_rope(direction);
+            Some(rope.clone())
+        }),
+    )
+}
+
+fn all_tail_positions<const L: usize>(moves: &[Move]) -> impl Iterator<Item = Position> + '_ {
+    all_rope_states::<L>(moves).map(|rope| rope.tail_position())
+}
+
+pub fn num_tail_positions<const L: usize>(moves: &[Move]) -> usize {
+    all_tail_positions::<L>(moves).collect::<HashSet<_>>().len()
+}
+
+pub fn num_tail_positions_dyn(moves: &[Move], length: usize) -> Result<usize, Error> {
+    if length == 0 {
+        return Err(err_msg("Rope must have at least one knot"));
+    }
+    let rope = DynRope::new(length);
+    Ok(chain(
         [rope.tail_position()],
         expand(moves).scan(rope, |rope, direction| {
             rope.move_rope(direction);
             Some(rope.tail_position())
         }),
     )
+    .collect::<HashSet<_>>()
+    .len())
 }
 
-fn num_tail_positions<const L: usize>(moves: &[Move]) -> usize {
-    all_tail_positions::<L>(moves).collect::<HashSet<_>>().len()
+pub fn visited_counts<const L: usize>(moves: &[Move]) -> [usize; L] {
+    let mut visited: [HashSet<Position>; L] = array::from_fn(|_| HashSet::new());
+    for rope in all_rope_states::<L>(moves) {
+        for (knot_visited, position) in visited.iter_mut().zip(rope.positions) {
+            knot_visited.insert(position);
+        }
+    }
+    visited.map(|knot_visited| knot_visited.len())
+}
+
+pub fn render_visited<const L: usize>(moves: &[Move]) -> String {
+    let mut visited = HashSet::new();
+    let mut knot_positions = vec![];
+    for rope in all_rope_states::<L>(moves) {
+        visited.insert(rope.tail_position());
+        knot_positions.extend(rope.positions);
+    }
+
+    let bounds = match Bounds::from(knot_positions).non_empty() {
+        Some(bounds) => *bounds,
+        None => return String::new(),
+    };
+
+    bounds
+        .iter_y()
+        .rev()
+        .map(|y| {
+            bounds
+                .iter_x()
+                .map(|x| {
+                    let position = Position { x, y };
+                    if position == Position::ORIGIN {
+                        's'
+                    } else if visited.contains(&position) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .chain(once('\n'))
+                .collect::<String>()
+        })
+        .collect()
 }
 
 pub struct Solver {}
@@ -166,3 +268,127 @@ impl super::Solver for Solver {
         (Some(part_one), Some(part_two))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        all_rope_states, num_tail_positions, num_tail_positions_dyn, render_visited, visited_counts,
+    };
+    use crate::Solver;
+
+    const SAMPLE: &str = "R 4
+U 4
+L 3
+D 1
+R 4
+D 1
+L 5
+R 2
+";
+
+    const LARGE_SAMPLE: &str = "R 5
+U 8
+L 8
+D 3
+R 17
+D 10
+L 25
+U 20
+";
+
+    #[test]
+    fn test_dyn_rope() {
+        for data in [SAMPLE, LARGE_SAMPLE] {
+            let moves = super::Solver::parse_input(data.to_string()).unwrap();
+            assert_eq!(
+                num_tail_positions_dyn(&moves, 2).unwrap(),
+                num_tail_positions::<2>(&moves)
+            );
+            assert_eq!(
+                num_tail_positions_dyn(&moves, 10).unwrap(),
+                num_tail_positions::<10>(&moves)
+            );
+        }
+
+        let moves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(num_tail_positions_dyn(&moves, 2).unwrap(), 13);
+        assert_eq!(num_tail_positions_dyn(&moves, 10).unwrap(), 1);
+
+        let moves = super::Solver::parse_input(LARGE_SAMPLE.to_string()).unwrap();
+        assert_eq!(num_tail_positions_dyn(&moves, 10).unwrap(), 36);
+        assert_eq!(
+            num_tail_positions_dyn(&moves, 7).unwrap(),
+            num_tail_positions::<7>(&moves)
+        );
+        assert_eq!(
+            num_tail_positions_dyn(&moves, 1).unwrap(),
+            num_tail_positions::<1>(&moves)
+        );
+        assert!(num_tail_positions_dyn(&moves, 0).is_err());
+    }
+
+    #[test]
+    fn test_render_visited() {
+        let moves = super::Solver::parse_input(LARGE_SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            render_visited::<10>(&moves),
+            "..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+#.........................
+#.............###.........
+#............#...#........
+.#..........#.....#.......
+..#..........#.....#......
+...#........#.......#.....
+....#......s.........#....
+.....#..............#.....
+......#............#......
+.......#..........#.......
+........#........#........
+.........########.........
+"
+        );
+    }
+
+    #[test]
+    fn test_visited_counts() {
+        let moves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let counts = visited_counts::<10>(&moves);
+        assert_eq!(counts[1], 13);
+        assert_eq!(counts[9], 1);
+
+        let moves = super::Solver::parse_input(LARGE_SAMPLE.to_string()).unwrap();
+        let counts = visited_counts::<10>(&moves);
+        assert_eq!(counts[1], num_tail_positions::<2>(&moves));
+        assert_eq!(counts[9], 36);
+    }
+
+    #[test]
+    fn test_diagonal_moves() {
+        let moves = super::Solver::parse_input("UR 4\n".to_string()).unwrap();
+        assert_eq!(num_tail_positions::<2>(&moves), 4);
+
+        let moves = super::Solver::parse_input("R 4\nUR 2\nDL 3\n".to_string()).unwrap();
+        assert_eq!(num_tail_positions::<2>(&moves), 6);
+
+        let data = "UR 5\nL 3\nDL 7\nDR 2\nR 9\nUL 6\nD 4\n";
+        let moves = super::Solver::parse_input(data.to_string()).unwrap();
+        for rope in all_rope_states::<10>(&moves) {
+            for knots in rope.positions.windows(2) {
+                assert!((knots[0].x - knots[1].x).abs() <= 1);
+                assert!((knots[0].y - knots[1].y).abs() <= 1);
+            }
+        }
+        assert_eq!(
+            num_tail_positions_dyn(&moves, 10).unwrap(),
+            num_tail_positions::<10>(&moves)
+        );
+    }
+}
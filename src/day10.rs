@@ -42,8 +42,12 @@ mod parse {
     }
 }
 
-use failure::Error;
+use failure::{err_msg, Error};
 use itertools::{chain, Either, Itertools};
+use std::io::{self, Write};
+
+use crate::common::write_ppm;
+use crate::report;
 
 use self::parse::parse_input;
 
@@ -68,23 +72,25 @@ fn as_single_cycle(commands: &[Command]) -> impl Iterator<Item = Command> + '_ {
     })
 }
 
-struct Screen<const W: usize, const H: usize> {
-    pixels: [[char; W]; H],
+struct Screen {
+    pixels: Vec<Vec<char>>,
+    width: usize,
+    height: usize,
 }
 
-impl<const W: usize, const H: usize> Default for Screen<W, H> {
-    fn default() -> Self {
+impl Screen {
+    fn new(width: usize, height: usize) -> Self {
         Screen {
-            pixels: [[' '; W]; H],
+            pixels: vec![vec![' '; width]; height],
+            width,
+            height,
         }
     }
-}
 
-impl<const W: usize, const H: usize> Screen<W, H> {
     fn get_draw_position(&self, cycle: i64) -> (usize, usize) {
         let index = cycle as usize - 1;
-        let x = index % W;
-        let y = (index / W) % H;
+        let x = index % self.width;
+        let y = (index / self.width) % self.height;
         (x, y)
     }
 
@@ -110,12 +116,33 @@ impl<const W: usize, const H: usize> Screen<W, H> {
     }
 }
 
-fn signal_strength(cycle: i64, x: i64) -> i64 {
-    cycle * x
+pub fn draw(commands: &[Command], width: usize, height: usize) -> Result<String, Error> {
+    if width == 0 || height == 0 {
+        return Err(err_msg(format!(
+            "Screen must be non-empty: {}x{}",
+            width, height
+        )));
+    }
+    Ok(Screen::new(width, height).draw(commands))
+}
+
+/// Writes a screen drawn by `draw` as a PPM image, with lit pixels in white and dark pixels in
+/// black, each scaled up to a `scale` by `scale` square.
+pub fn render_ppm<W: Write>(screen: &str, scale: usize, writer: W) -> io::Result<()> {
+    let rows = screen.lines().map(str::as_bytes).collect::<Vec<_>>();
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+
+    write_ppm(writer, width, rows.len(), scale, |x, y| {
+        if rows[y].get(x) == Some(&b'#') {
+            [255; 3]
+        } else {
+            [0; 3]
+        }
+    })
 }
 
-fn record_signal_strength(cycle: i64) -> bool {
-    (cycle - 20) % 40 == 0
+fn signal_strength(cycle: i64, x: i64) -> i64 {
+    cycle * x
 }
 
 fn positions(commands: &[Command]) -> impl Iterator<Item = (i64, i64)> + '_ {
@@ -128,16 +155,85 @@ fn positions(commands: &[Command]) -> impl Iterator<Item = (i64, i64)> + '_ {
     )
 }
 
-fn total_signal_strength(commands: &[Command]) -> i64 {
-    positions(commands)
+/// Returns the `(cycle, x)` register value during every cycle of the program.
+pub fn register_values(commands: &[Command]) -> Vec<(i64, i64)> {
+    positions(commands).collect()
+}
+
+pub fn total_signal_strength_with(
+    commands: &[Command],
+    first: i64,
+    interval: i64,
+) -> Result<i64, Error> {
+    if interval <= 0 {
+        return Err(err_msg(format!(
+            "Signal interval must be positive: {}",
+            interval
+        )));
+    }
+    Ok(positions(commands)
         .filter_map(|(cycle, x)| {
-            if record_signal_strength(cycle) {
+            if cycle >= first && (cycle - first) % interval == 0 {
                 Some(signal_strength(cycle, x))
             } else {
                 None
             }
         })
-        .sum()
+        .sum())
+}
+
+fn total_signal_strength(commands: &[Command]) -> Result<i64, Error> {
+    total_signal_strength_with(commands, 20, 40)
+}
+
+const LETTER_WIDTH: usize = 5;
+
+const GLYPHS: [(char, &str); 17] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+pub fn decode_letters(screen: &str) -> Option<String> {
+    let rows = screen
+        .lines()
+        .map(|row| {
+            row.chars()
+                .map(|c| if c == '#' { '#' } else { '.' })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let width = rows.iter().map(|row| row.len()).max()?;
+
+    (0..(width + 1) / LETTER_WIDTH)
+        .map(|index| {
+            let glyph = rows
+                .iter()
+                .flat_map(|row| {
+                    (0..LETTER_WIDTH - 1)
+                        .map(move |x| row.get(index * LETTER_WIDTH + x).cloned().unwrap_or('.'))
+                })
+                .collect::<String>();
+            GLYPHS
+                .iter()
+                .find(|(_, pattern)| *pattern == glyph)
+                .map(|(letter, _)| *letter)
+        })
+        .collect()
 }
 
 pub struct Solver {}
@@ -150,8 +246,154 @@ impl super::Solver for Solver {
     }
 
     fn solve(commands: Self::Problem) -> (Option<String>, Option<String>) {
-        let part_one = total_signal_strength(&commands).to_string();
-        let part_two = Screen::<40, 6>::default().draw(&commands);
-        (Some(part_one), Some(part_two))
+        let part_one = total_signal_strength(&commands);
+        let part_two =
+            draw(&commands, 40, 6).map(|screen| decode_letters(&screen).unwrap_or(screen));
+        (report(1, part_one), report(2, part_two))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        decode_letters, draw, register_values, render_ppm, total_signal_strength,
+        total_signal_strength_with, Command, GLYPHS,
+    };
+    use crate::Solver;
+
+    fn glyph(letter: char) -> &'static str {
+        GLYPHS.iter().find(|(l, _)| *l == letter).unwrap().1
+    }
+
+    fn render_message(message: &str) -> Vec<Vec<bool>> {
+        (0..6)
+            .map(|y| {
+                message
+                    .chars()
+                    .flat_map(|letter| {
+                        glyph(letter)[y * 4..(y + 1) * 4]
+                            .chars()
+                            .map(|c| c == '#')
+                            .chain([false])
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn program_for(pixels: &[Vec<bool>]) -> Vec<Command> {
+        let pixels = pixels.iter().flatten().cloned().collect::<Vec<_>>();
+        let mut x = 1;
+        let mut commands = vec![];
+        for (index, pair) in pixels.chunks(2).enumerate() {
+            let col = (index * 2 % 40) as i64;
+            let target = match (pair[0], pair[1]) {
+                (true, true) => col,
+                (true, false) => col - 1,
+                (false, true) => col + 2,
+                (false, false) => -10,
+            };
+            if index == 0 {
+                assert!(pair[0] && pair[1]);
+            } else {
+                commands.push(Command::Add(target - x));
+                x = target;
+            }
+        }
+        commands.push(Command::Noop);
+        commands
+    }
+
+    #[test]
+    fn test_decode_letters() {
+        let commands = program_for(&render_message("ZEBRAHUG"));
+        let screen = draw(&commands, 40, 6).unwrap();
+        assert_eq!(decode_letters(&screen), Some("ZEBRAHUG".to_string()));
+        assert_eq!(decode_letters(&screen.replace('#', "X")), None);
+    }
+
+    #[test]
+    fn test_render_ppm() {
+        let commands = program_for(&render_message("ZEBRAHUG"));
+        let screen = draw(&commands, 40, 6).unwrap();
+
+        let mut output = vec![];
+        render_ppm(&screen, 3, &mut output).unwrap();
+        let header = b"P6\n120 18\n255\n";
+        assert_eq!(&output[..header.len()], header);
+
+        let pixels = &output[header.len()..];
+        assert_eq!(pixels.len(), 120 * 18 * 3);
+        assert!(pixels.iter().all(|&value| value == 0 || value == 255));
+        let num_lit = screen.chars().filter(|&c| c == '#').count();
+        assert_eq!(
+            pixels.chunks(3).filter(|pixel| pixel == &[255; 3]).count(),
+            num_lit * 3 * 3
+        );
+
+        // The top left pixel of the Z is lit, and the gap after it is dark.
+        assert_eq!(&pixels[..3], [255; 3]);
+        assert_eq!(&pixels[4 * 3 * 3..4 * 3 * 3 + 3], [0; 3]);
+    }
+
+    #[test]
+    fn test_signal_strength() {
+        let noops = vec![Command::Noop; 240];
+        assert_eq!(total_signal_strength(&noops).unwrap(), 720);
+        assert_eq!(total_signal_strength_with(&noops, 1, 100).unwrap(), 303);
+
+        let adds = vec![Command::Add(1); 120];
+        assert_eq!(total_signal_strength(&adds).unwrap(), 57200);
+        assert_eq!(
+            total_signal_strength_with(&adds, 20, 40).unwrap(),
+            total_signal_strength(&adds).unwrap()
+        );
+
+        assert!(total_signal_strength_with(&adds, 20, 0).is_err());
+        assert!(total_signal_strength_with(&adds, 20, -40).is_err());
+    }
+
+    #[test]
+    fn test_empty_screen() {
+        let noops = vec![Command::Noop; 20];
+        assert!(draw(&noops, 0, 6).is_err());
+        assert!(draw(&noops, 40, 0).is_err());
+        assert_eq!(draw(&[], 1, 1).unwrap(), "#");
+    }
+
+    #[test]
+    fn test_defaults() {
+        let commands = program_for(&render_message("ZEBRAHUG"));
+        assert_eq!(
+            super::Solver::solve(commands.into_boxed_slice()).1,
+            Some("ZEBRAHUG".to_string())
+        );
+
+        let noops = vec![Command::Noop; 20];
+        assert_eq!(draw(&noops, 10, 2).unwrap(), "###       \n###       ");
+    }
+
+    #[test]
+    fn test_register_values() {
+        let adds = vec![Command::Add(1); 120];
+        let values = register_values(&adds);
+        assert_eq!(values.len(), 241);
+        assert_eq!(
+            [20, 60, 100, 140, 180, 220].map(|cycle| values[cycle - 1]),
+            [
+                (20, 10),
+                (60, 30),
+                (100, 50),
+                (140, 70),
+                (180, 90),
+                (220, 110)
+            ]
+        );
+
+        let commands = [Command::Noop, Command::Add(3), Command::Add(-5)];
+        assert_eq!(
+            register_values(&commands),
+            vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4), (6, -1)]
+        );
     }
 }
@@ -1,3 +1,8 @@
+use std::{
+    fmt::Debug,
+    ops::{Add, Div, Mul, Rem, Sub},
+};
+
 use failure::{err_msg, Error};
 
 mod parse {
@@ -28,15 +33,16 @@ mod parse {
         alt((value(Value::Old, tag("old")), map(unsigned, Value::Literal)))(input)
     }
 
-    fn operator(input: &str) -> IResult<&str, impl Fn(Value, Value) -> Operation> {
-        map(alt((tag("+"), tag("*"))), |op: &str| {
-            let op = op.to_string();
-            move |x, y| match op.as_str() {
-                "+" => Operation::Add(x, y),
-                "*" => Operation::Multiply(x, y),
-                _ => panic!("Unexpected operator: {}", op),
-            }
-        })(input)
+    type Operator = fn(Value, Value) -> Operation;
+
+    fn operator(input: &str) -> IResult<&str, Operator> {
+        alt((
+            value(Operation::Add as Operator, tag("+")),
+            value(Operation::Subtract as Operator, tag("-")),
+            value(Operation::Multiply as Operator, tag("*")),
+            value(Operation::Divide as Operator, tag("/")),
+            value(Operation::Modulo as Operator, tag("%")),
+        ))(input)
     }
 
     fn test_divisible(input: &str) -> IResult<&str, u64> {
@@ -48,12 +54,16 @@ mod parse {
     }
 
     fn operation(input: &str) -> IResult<&str, Operation> {
-        map(
+        map_res(
             preceded(
                 tag("new = "),
                 tuple((op_value, delimited(tag(" "), operator, tag(" ")), op_value)),
             ),
-            |(x, op, y)| op(x, y),
+            |(x, op, y)| match op(x, y) {
+                Operation::Divide(_, Value::Literal(0))
+                | Operation::Modulo(_, Value::Literal(0)) => Err(err_msg("Division by zero")),
+                operation => Ok(operation),
+            },
         )(input)
     }
 
@@ -88,121 +98,290 @@ mod parse {
             .map(|(_, ms)| ms)
             .map_err(|err| err_msg(format!("Failed to parse monkeys: {}", err)))
     }
+
+    #[cfg(test)]
+    mod test {
+        use super::operation;
+        use crate::day11::{Operation, Value};
+
+        #[test]
+        fn test_parse_operation() {
+            assert_eq!(
+                operation("new = old - 3"),
+                Ok(("", Operation::Subtract(Value::Old, Value::Literal(3))))
+            );
+            assert_eq!(
+                operation("new = old / 2"),
+                Ok(("", Operation::Divide(Value::Old, Value::Literal(2))))
+            );
+            assert_eq!(
+                operation("new = old % 7"),
+                Ok(("", Operation::Modulo(Value::Old, Value::Literal(7))))
+            );
+            assert!(operation("new = old / 0").is_err());
+        }
+    }
 }
 
 use parse::parse_input;
 
+use crate::report;
+
+/// A type that can hold worry levels, the puzzle itself only needs `u64` but wider types can be
+/// used to follow the worry levels without reducing them.
+pub trait Worry:
+    Clone
+    + Debug
+    + Ord
+    + From<u64>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+{
+}
+
+impl<W> Worry for W where
+    W: Clone
+        + Debug
+        + Ord
+        + From<u64>
+        + Add<Output = W>
+        + Sub<Output = W>
+        + Mul<Output = W>
+        + Div<Output = W>
+        + Rem<Output = W>
+{
+}
+
+fn is_zero<W: Worry>(value: &W) -> bool {
+    *value == W::from(0)
+}
+
 #[derive(Debug, Clone)]
-pub struct Monkey {
+pub struct Monkey<W = u64> {
     index: usize,
-    items: Vec<u64>,
-    operation: Operation,
-    test_divisible: u64,
+    items: Vec<W>,
+    operation: Operation<W>,
+    test_divisible: W,
     test_pass_throw: usize,
     test_fail_throw: usize,
     inspections: usize,
 }
 
 impl Monkey {
-    fn take_turn(&mut self, reduce_worry: bool, modulo: u64) -> Vec<Throw> {
+    /// Converts the worry levels into another type.
+    pub fn with_worry<W: Worry>(self) -> Monkey<W> {
+        Monkey {
+            index: self.index,
+            items: self.items.into_iter().map(W::from).collect(),
+            operation: self.operation.with_worry(),
+            test_divisible: self.test_divisible.into(),
+            test_pass_throw: self.test_pass_throw,
+            test_fail_throw: self.test_fail_throw,
+            inspections: self.inspections,
+        }
+    }
+}
+
+impl<W: Worry> Monkey<W> {
+    fn take_turn(
+        &mut self,
+        worry_divisor: Option<&W>,
+        modulo: Option<&W>,
+    ) -> Result<Vec<Throw<W>>, Error> {
         self.inspections += self.items.len();
         self.items
             .drain(..)
-            .map(|mut worry_level| {
-                worry_level = self.operation.apply(worry_level);
+            .map(|worry_level| {
+                let mut worry_level = self.operation.apply(worry_level)?;
 
-                if reduce_worry {
-                    worry_level /= 3;
+                if let Some(divisor) = worry_divisor {
+                    worry_level = worry_level / divisor.clone();
                 }
 
-                worry_level %= modulo;
+                if let Some(modulo) = modulo {
+                    worry_level = worry_level % modulo.clone();
+                }
 
-                let monkey = if worry_level % self.test_divisible == 0 {
+                let monkey = if is_zero(&(worry_level.clone() % self.test_divisible.clone())) {
                     self.test_pass_throw
                 } else {
                     self.test_fail_throw
                 };
 
-                Throw {
+                Ok(Throw {
                     monkey,
                     item: worry_level,
-                }
+                })
             })
             .collect()
     }
 
-    fn catch(&mut self, item: u64) {
+    fn catch(&mut self, item: W) {
         self.items.push(item);
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Value {
+enum Value<W = u64> {
     Old,
-    Literal(u64),
+    Literal(W),
 }
 
 impl Value {
-    fn value(&self, old: u64) -> u64 {
+    fn with_worry<W: Worry>(self) -> Value<W> {
+        match self {
+            Value::Old => Value::Old,
+            Value::Literal(val) => Value::Literal(val.into()),
+        }
+    }
+}
+
+impl<W: Worry> Value<W> {
+    fn value(&self, old: &W) -> W {
         match self {
-            Value::Old => old,
-            Value::Literal(val) => *val,
+            Value::Old => old.clone(),
+            Value::Literal(val) => val.clone(),
         }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Operation {
-    Add(Value, Value),
-    Multiply(Value, Value),
+enum Operation<W = u64> {
+    Add(Value<W>, Value<W>),
+    Subtract(Value<W>, Value<W>),
+    Multiply(Value<W>, Value<W>),
+    Divide(Value<W>, Value<W>),
+    Modulo(Value<W>, Value<W>),
 }
 
 impl Operation {
-    fn apply(&self, old: u64) -> u64 {
+    fn with_worry<W: Worry>(self) -> Operation<W> {
+        match self {
+            Operation::Add(x, y) => Operation::Add(x.with_worry(), y.with_worry()),
+            Operation::Subtract(x, y) => Operation::Subtract(x.with_worry(), y.with_worry()),
+            Operation::Multiply(x, y) => Operation::Multiply(x.with_worry(), y.with_worry()),
+            Operation::Divide(x, y) => Operation::Divide(x.with_worry(), y.with_worry()),
+            Operation::Modulo(x, y) => Operation::Modulo(x.with_worry(), y.with_worry()),
+        }
+    }
+}
+
+impl<W: Worry> Operation<W> {
+    /// Whether the result, taken modulo any number, only depends on the operands taken modulo
+    /// that number.
+    fn preserves_modulo(&self) -> bool {
+        matches!(self, Operation::Add(..) | Operation::Multiply(..))
+    }
+
+    fn apply(&self, old: W) -> Result<W, Error> {
         match self {
-            Operation::Add(x, y) => x.value(old) + y.value(old),
-            Operation::Multiply(x, y) => x.value(old) * y.value(old),
+            Operation::Add(x, y) => Ok(x.value(&old) + y.value(&old)),
+            Operation::Subtract(x, y) => {
+                let (x, y) = (x.value(&old), y.value(&old));
+                if x < y {
+                    return Err(err_msg(format!("Worry level underflow: {:?}", self)));
+                }
+                Ok(x - y)
+            }
+            Operation::Multiply(x, y) => Ok(x.value(&old) * y.value(&old)),
+            Operation::Divide(x, y) | Operation::Modulo(x, y) => {
+                let (x, y) = (x.value(&old), y.value(&old));
+                if is_zero(&y) {
+                    return Err(err_msg(format!("Division by zero: {:?}", self)));
+                }
+                Ok(match self {
+                    Operation::Divide(..) => x / y,
+                    _ => x % y,
+                })
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Throw {
-    monkey: usize,
-    item: u64,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throw<W = u64> {
+    pub monkey: usize,
+    pub item: W,
 }
 
-struct Executor {
-    monkeys: Box<[Monkey]>,
-    reduce_worry: bool,
-    modulo: u64,
+pub struct Executor<W = u64> {
+    monkeys: Box<[Monkey<W>]>,
+    worry_divisor: Option<W>,
+    modulo: Option<W>,
+    throws: Box<[usize]>,
 }
 
-impl Executor {
-    fn new(monkeys: Box<[Monkey]>, reduce_worry: bool) -> Self {
-        let modulo = monkeys.iter().map(|monkey| monkey.test_divisible).product();
+impl<W: Worry> Executor<W> {
+    /// Worry levels are reduced modulo the product of the monkeys' tests unless any monkey
+    /// subtracts, divides or takes a remainder, since the reduced levels would then route items
+    /// differently; such inputs need a worry type wide enough to hold the full levels.
+    pub fn new(monkeys: Box<[Monkey<W>]>, reduce_worry: bool) -> Self {
+        let reducible = monkeys
+            .iter()
+            .all(|monkey| monkey.operation.preserves_modulo());
+        let modulo = monkeys
+            .iter()
+            .map(|monkey| monkey.test_divisible.clone())
+            .fold(W::from(1), |product, divisor| product * divisor);
+        let throws = vec![0; monkeys.len()].into_boxed_slice();
         Executor {
             monkeys,
-            reduce_worry,
-            modulo,
+            worry_divisor: if reduce_worry { Some(W::from(3)) } else { None },
+            modulo: reducible.then_some(modulo),
+            throws,
+        }
+    }
+
+    /// Replaces the divisor of 3 used to reduce worry after each inspection.
+    ///
+    /// The divisor is ignored if worry isn't being reduced, since dividing the worry levels
+    /// would stop them being reduced modulo the product of the monkeys' tests.
+    pub fn with_divisor(mut self, divisor: u64) -> Result<Self, Error> {
+        if divisor == 0 {
+            return Err(err_msg("Worry divisor must be non-zero"));
         }
+        if self.worry_divisor.is_some() {
+            self.worry_divisor = Some(divisor.into());
+        }
+        Ok(self)
+    }
+
+    /// Keeps the full worry levels rather than reducing them modulo the product of the monkeys'
+    /// tests, which needs a worry type wide enough to hold them.
+    pub fn without_modulo(mut self) -> Self {
+        self.modulo = None;
+        self
     }
 
-    fn execute_round(&mut self) {
+    pub fn execute_round_traced(&mut self) -> Result<Vec<(usize, Throw<W>)>, Error> {
+        let mut trace = vec![];
         for index in 0..self.monkeys.len() {
-            for throw in self.monkeys[index].take_turn(self.reduce_worry, self.modulo) {
-                self.monkeys[throw.monkey].catch(throw.item);
+            for throw in
+                self.monkeys[index].take_turn(self.worry_divisor.as_ref(), self.modulo.as_ref())?
+            {
+                self.monkeys[throw.monkey].catch(throw.item.clone());
+                self.throws[index] += 1;
+                trace.push((index, throw));
             }
         }
+        Ok(trace)
+    }
+
+    fn execute_round(&mut self) -> Result<(), Error> {
+        self.execute_round_traced().map(|_| ())
     }
 
-    fn execute(&mut self, rounds: usize) {
+    pub fn execute(&mut self, rounds: usize) -> Result<(), Error> {
         for _ in 0..rounds {
-            self.execute_round()
+            self.execute_round()?;
         }
+        Ok(())
     }
 
-    fn count_inspections(&self) -> Box<[usize]> {
+    pub fn count_inspections(&self) -> Box<[usize]> {
         self.monkeys
             .iter()
             .map(|monkey| monkey.inspections)
@@ -210,17 +389,26 @@ impl Executor {
             .into_boxed_slice()
     }
 
-    fn get_monkey_business(&self) -> usize {
+    pub fn throw_counts(&self) -> Box<[usize]> {
+        self.throws.clone()
+    }
+
+    pub fn get_monkey_business(&self) -> usize {
         let mut num_inspections = self.count_inspections();
         num_inspections.sort_unstable_by(|a, b| a.cmp(b).reverse());
         num_inspections[0] * num_inspections[1]
     }
 }
 
-fn get_monkey_business(monkeys: Box<[Monkey]>, reduce_worry: bool, rounds: usize) -> usize {
-    let mut executor = Executor::new(monkeys, reduce_worry);
-    executor.execute(rounds);
-    executor.get_monkey_business()
+pub fn monkey_business<W: Worry>(
+    monkeys: Box<[Monkey<W>]>,
+    reduce_worry: bool,
+    rounds: usize,
+    divisor: u64,
+) -> Result<usize, Error> {
+    let mut executor = Executor::new(monkeys, reduce_worry).with_divisor(divisor)?;
+    executor.execute(rounds)?;
+    Ok(executor.get_monkey_business())
 }
 
 pub struct Solver {}
@@ -242,8 +430,162 @@ impl super::Solver for Solver {
     }
 
     fn solve(monkeys: Self::Problem) -> (Option<String>, Option<String>) {
-        let part_one = get_monkey_business(monkeys.clone(), true, 20).to_string();
-        let part_two = get_monkey_business(monkeys, false, 10000).to_string();
-        (Some(part_one), Some(part_two))
+        let part_one = monkey_business(monkeys.clone(), true, 20, 3);
+        let part_two = monkey_business(monkeys, false, 10000, 3);
+        (report(1, part_one), report(2, part_two))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{monkey_business, Executor, Monkey, Throw};
+    use crate::Solver;
+
+    const SAMPLE: &str = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1
+";
+
+    #[test]
+    fn test_monkey_business() {
+        let monkeys = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            monkey_business(monkeys.clone(), true, 20, 3).unwrap(),
+            10605
+        );
+        assert_eq!(
+            monkey_business(monkeys, false, 10000, 3).unwrap(),
+            2713310158
+        );
+    }
+
+    #[test]
+    fn test_divisor() {
+        let monkeys = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert!(monkey_business(monkeys.clone(), true, 20, 0).is_err());
+        assert!(monkey_business(monkeys.clone(), false, 20, 0).is_err());
+
+        assert_ne!(
+            monkey_business(monkeys.clone(), true, 20, 2).unwrap(),
+            monkey_business(monkeys.clone(), true, 20, 3).unwrap()
+        );
+        // Without reducing worry the divisor has no effect.
+        assert_eq!(
+            monkey_business(monkeys.clone(), false, 1000, 2).unwrap(),
+            monkey_business(monkeys, false, 1000, 3).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_throw_trace() {
+        let monkeys = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let mut executor = Executor::new(monkeys, true);
+        let trace = executor.execute_round_traced().unwrap();
+        assert_eq!(
+            trace,
+            [
+                (0, 3, 500),
+                (0, 3, 620),
+                (1, 0, 20),
+                (1, 0, 23),
+                (1, 0, 27),
+                (1, 0, 26),
+                (2, 1, 2080),
+                (2, 3, 1200),
+                (2, 3, 3136),
+                (3, 1, 25),
+                (3, 1, 167),
+                (3, 1, 207),
+                (3, 1, 401),
+                (3, 1, 1046),
+            ]
+            .map(|(from, monkey, item)| (from, Throw { monkey, item }))
+        );
+
+        executor.execute(19).unwrap();
+        assert_eq!(executor.throw_counts(), executor.count_inspections());
+        assert_eq!(*executor.throw_counts(), [101, 95, 7, 105]);
+    }
+
+    #[test]
+    fn test_wider_worry() {
+        let monkeys = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let wide = monkeys
+            .into_vec()
+            .into_iter()
+            .map(Monkey::with_worry::<u128>)
+            .collect::<Box<[_]>>();
+        assert_eq!(
+            monkey_business(wide.clone(), false, 10000, 3).unwrap(),
+            2713310158
+        );
+
+        // Part one's worry levels stay small enough to follow without the modulo.
+        let mut executor = Executor::new(wide, true).without_modulo();
+        executor.execute(20).unwrap();
+        assert_eq!(executor.get_monkey_business(), 10605);
+    }
+
+    #[test]
+    fn test_division_not_reduced() {
+        let input = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 1
+
+Monkey 1:
+  Starting items: 54
+  Operation: new = old / 2
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1
+";
+        let monkeys = super::Solver::parse_input(input.to_string()).unwrap();
+        let mut reduced = Executor::new(monkeys.clone(), false);
+        let mut unreduced = Executor::new(monkeys, false).without_modulo();
+        let mut largest = 0;
+        for _ in 0..5 {
+            let trace = reduced.execute_round_traced().unwrap();
+            assert_eq!(trace, unreduced.execute_round_traced().unwrap());
+            largest = trace
+                .iter()
+                .map(|(_, throw)| throw.item)
+                .fold(largest, u64::max);
+        }
+        // The items grow past the product of the tests, so reducing them would change the trace.
+        assert!(largest > 23 * 19 * 17);
     }
 }
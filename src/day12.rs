@@ -1,34 +1,20 @@
-use std::collections::HashSet;
 use std::{cmp::max, collections::HashMap, fmt::Debug, hash::Hash, str::FromStr};
 
 use crate::a_star;
-use crate::common::{Direction, Position};
+use crate::common::{Bounds, Direction, Position};
+use crate::parsers::positioned_chars;
+use crate::render::render_grid;
+use crate::report;
 use failure::{err_msg, Error};
 
 pub struct HeightMap {
     heights: HashMap<Position, u8>,
     start: Position,
     end: Position,
-    #[allow(unused)]
     top_left: Position,
-    #[allow(unused)]
     bottom_right: Position,
 }
 
-fn read_height_chars(input: &str) -> impl Iterator<Item = (Position, char)> + '_ {
-    input.lines().enumerate().flat_map(|(y, row)| {
-        row.chars().enumerate().map(move |(x, h)| {
-            (
-                Position {
-                    x: x as i64,
-                    y: y as i64,
-                },
-                h,
-            )
-        })
-    })
-}
-
 fn get_height(h: char) -> Result<u8, Error> {
     let actual_h = match h {
         'S' => 'a',
@@ -58,7 +44,7 @@ impl FromStr for HeightMap {
         let mut max_x = 0;
         let mut max_y = 0;
 
-        for (position, h) in read_height_chars(s) {
+        for (position, h) in positioned_chars(s, 0, 0) {
             let height = get_height(h)?;
             if is_start(h) {
                 start = Some(position);
@@ -86,10 +72,17 @@ impl FromStr for HeightMap {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Search {
+    Forward,
+    Reverse,
+}
+
 #[derive(Clone)]
 struct State<'a> {
     height_map: &'a HeightMap,
     position: Position,
+    search: Search,
 }
 
 impl<'a> Debug for State<'a> {
@@ -103,6 +96,26 @@ impl<'a> State<'a> {
         State {
             height_map,
             position,
+            search: Search::Forward,
+        }
+    }
+
+    fn reverse(height_map: &'a HeightMap, position: Position) -> Self {
+        State {
+            height_map,
+            position,
+            search: Search::Reverse,
+        }
+    }
+
+    fn height(&self) -> u8 {
+        *self.height_map.heights.get(&self.position).unwrap()
+    }
+
+    fn can_step(&self, height: u8) -> bool {
+        match self.search {
+            Search::Forward => height <= self.height() + 1,
+            Search::Reverse => self.height() <= height + 1,
         }
     }
 }
@@ -123,29 +136,28 @@ impl<'a> Hash for State<'a> {
 
 impl<'a> a_star::State for State<'a> {
     fn heuristic(&self) -> u64 {
-        return (self.height_map.heights.get(&self.height_map.end).unwrap()
-            - self.height_map.heights.get(&self.position).unwrap()) as u64;
-        // TODO: Figure out why this doesn't work
-        #[allow(unreachable_code)]
+        if self.search == Search::Reverse {
+            return 0;
+        }
+
+        let end_height = self.height_map.heights.get(&self.height_map.end).unwrap();
         max(
             self.position.manhattan_distance_to(&self.height_map.end),
-            (self.height_map.heights.get(&self.height_map.end).unwrap()
-                - self.height_map.heights.get(&self.position).unwrap()) as u64,
+            end_height.saturating_sub(self.height()) as u64,
         )
     }
 
     fn successors(&self) -> Vec<(u64, Self)> {
-        let current_height = *self.height_map.heights.get(&self.position).unwrap();
         self.position
             .adjacent()
             .filter_map(|position| {
                 self.height_map.heights.get(&position).and_then(|&height| {
-                    if height <= current_height + 1 {
+                    if self.can_step(height) {
                         Some((
                             1_u64,
                             State {
-                                height_map: self.height_map,
                                 position,
+                                ..self.clone()
                             },
                         ))
                     } else {
@@ -157,78 +169,54 @@ impl<'a> a_star::State for State<'a> {
     }
 
     fn is_end(&self) -> bool {
-        self.position == self.height_map.end
+        match self.search {
+            Search::Forward => self.position == self.height_map.end,
+            Search::Reverse => self.height() == 0,
+        }
     }
 }
 
-#[allow(unused)]
 fn height_char(height: u8) -> char {
     (b'a' + height) as char
 }
 
-#[allow(unused)]
-fn display_route(height_map: &HeightMap, route: Vec<State<'_>>) {
+pub fn render_route(height_map: &HeightMap, route: &[Position]) -> String {
     let directions: HashMap<Position, Direction> = route
         .iter()
         .zip(route.iter().skip(1))
-        .map(|(state, next_state)| {
-            (
-                state.position,
-                state.position.direction_to(&next_state.position).unwrap(),
-            )
-        })
+        .map(|(position, next_position)| (*position, position.direction_to(next_position).unwrap()))
         .collect();
-    for y in height_map.top_left.y..=height_map.bottom_right.y {
-        let row: String = (height_map.top_left.x..=height_map.bottom_right.x)
-            .map(|x| Position { x, y })
-            .map(|position| {
-                directions
-                    .get(&position)
-                    .map(|dir| dir.as_char())
-                    .or_else(|| height_map.heights.get(&position).cloned().map(height_char))
-                    .unwrap_or(' ')
-            })
-            .collect();
-        println!("{}", row);
-    }
+    let bounds = Bounds::from([height_map.top_left, height_map.bottom_right]);
+    render_grid(&bounds, |position| {
+        directions
+            .get(&position)
+            .map(|dir| dir.as_char())
+            .or_else(|| height_map.heights.get(&position).cloned().map(height_char))
+            .unwrap_or(' ')
+    })
 }
 
-fn find_shortest_route_from(
-    height_map: &HeightMap,
-    start: Position,
-) -> Result<u64, HashSet<Position>> {
-    let start = State::new(height_map, start);
-
-    a_star::solve(start)
-        .map(|(distance, _route)| distance)
-        .map_err(|visited| visited.into_iter().map(|state| state.position).collect())
+#[allow(unused)]
+fn display_route(height_map: &HeightMap, route: &[Position]) {
+    print!("{}", render_route(height_map, route));
 }
 
-fn all_start_points(height_map: &HeightMap) -> Vec<Position> {
-    height_map
-        .heights
-        .iter()
-        .filter_map(|(position, height)| if *height == 0 { Some(*position) } else { None })
-        .collect()
+pub fn shortest_path(height_map: &HeightMap, start: Position) -> Option<Vec<Position>> {
+    a_star::solve(State::new(height_map, start))
+        .ok()
+        .map(|(_distance, route)| route.into_iter().map(|state| state.position).collect())
 }
 
-fn find_shortest_route(height_map: &HeightMap, mut starts: Vec<Position>) -> Option<u64> {
-    let mut best = None;
-
-    while let Some(start) = starts.pop() {
-        match find_shortest_route_from(height_map, start) {
-            Ok(distance) => {
-                if best.map(|best| distance < best).unwrap_or(true) {
-                    best = Some(distance)
-                }
-            }
-            Err(visited) => {
-                starts.retain(|start| !visited.contains(start));
-            }
-        }
-    }
+fn find_shortest_route(height_map: &HeightMap, start: Position) -> Option<u64> {
+    a_star::solve(State::new(height_map, start))
+        .ok()
+        .map(|(distance, _route)| distance)
+}
 
-    best
+fn find_shortest_route_from_lowest(height_map: &HeightMap) -> Option<u64> {
+    a_star::solve(State::reverse(height_map, height_map.end))
+        .ok()
+        .map(|(distance, _route)| distance)
 }
 
 pub struct Solver {}
@@ -241,14 +229,88 @@ impl super::Solver for Solver {
     }
 
     fn solve(height_map: Self::Problem) -> (Option<String>, Option<String>) {
-        let part_one = find_shortest_route(&height_map, vec![height_map.start])
-            .expect("Failed to solve part one")
-            .to_string();
+        let part_one = find_shortest_route(&height_map, height_map.start)
+            .ok_or_else(|| err_msg("No route from start to end"));
+
+        let part_two = find_shortest_route_from_lowest(&height_map)
+            .ok_or_else(|| err_msg("No route from lowest point to end"));
 
-        let part_two = find_shortest_route(&height_map, all_start_points(&height_map))
-            .expect("Failed to solve part one")
-            .to_string();
+        (report(1, part_one), report(2, part_two))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        find_shortest_route, find_shortest_route_from_lowest, render_route, shortest_path, State,
+    };
+    use crate::{a_star::State as _, Solver};
+
+    const SAMPLE: &str = "Sabqponm
+abcryxxl
+accszExk
+acctuvwj
+abdefghi
+";
+
+    #[test]
+    fn test_part_one() {
+        let height_map = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(find_shortest_route(&height_map, height_map.start), Some(31));
+    }
+
+    #[test]
+    fn test_part_two() {
+        let height_map = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(find_shortest_route_from_lowest(&height_map), Some(29));
+    }
 
-        (Some(part_one), Some(part_two))
+    #[test]
+    fn test_shortest_path() {
+        let height_map = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let path = shortest_path(&height_map, height_map.start).unwrap();
+        assert_eq!(path.len(), 31 + 1);
+        assert_eq!(path.first(), Some(&height_map.start));
+        assert_eq!(path.last(), Some(&height_map.end));
+
+        let rendered = render_route(&height_map, &path);
+        assert_eq!(rendered.lines().count(), 5);
+        assert_eq!(rendered.chars().filter(|c| "^>v<".contains(*c)).count(), 31);
+    }
+
+    #[test]
+    fn test_no_route() {
+        let data = "Sabcc
+abcdz
+bczzz
+cdzEz
+"
+        .to_string();
+        let height_map = super::Solver::parse_input(data).unwrap();
+        assert_eq!(find_shortest_route(&height_map, height_map.start), None);
+        assert_eq!(find_shortest_route_from_lowest(&height_map), None);
+        assert_eq!(super::Solver::solve(height_map), (None, None));
+    }
+
+    #[test]
+    fn test_heuristic_above_end() {
+        // Lower the end so that most of the map is higher than it, which the heuristic must
+        // still underestimate the remaining distance for.
+        let mut height_map = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        height_map.heights.insert(height_map.end, 0);
+
+        let positions = height_map.heights.keys().cloned().collect::<Vec<_>>();
+        for position in positions {
+            let heuristic = State::new(&height_map, position).heuristic();
+            if let Some(distance) = find_shortest_route(&height_map, position) {
+                assert!(
+                    heuristic <= distance,
+                    "Heuristic {} exceeds distance {} from {:?}",
+                    heuristic,
+                    distance,
+                    position
+                );
+            }
+        }
     }
 }
@@ -5,10 +5,10 @@ mod parse {
     use nom::{
         branch::alt,
         bytes::complete::tag,
-        character::complete::newline,
+        character::complete::{line_ending, multispace0, multispace1, space0},
         combinator::{all_consuming, map},
         multi::{separated_list0, separated_list1},
-        sequence::{delimited, terminated, tuple},
+        sequence::{delimited, separated_pair, tuple},
         IResult,
     };
 
@@ -31,11 +31,17 @@ mod parse {
     }
 
     fn pair(input: &str) -> IResult<&str, (Packet, Packet)> {
-        tuple((terminated(packet, newline), terminated(packet, newline)))(input)
+        separated_pair(packet, tuple((space0, line_ending, space0)), packet)(input)
     }
 
     fn pairs(input: &str) -> IResult<&str, Vec<(Packet, Packet)>> {
-        separated_list1(newline, pair)(input)
+        delimited(multispace0, separated_list1(multispace1, pair), multispace0)(input)
+    }
+
+    pub fn parse_packet(input: &str) -> Result<Packet, Error> {
+        all_consuming(delimited(multispace0, packet, multispace0))(input)
+            .map(|(_, packet)| packet)
+            .map_err(|err| err_msg(format!("Failed to parse packet: {}", err)))
     }
 
     pub fn parse_input(input: &str) -> Result<Vec<(Packet, Packet)>, Error> {
@@ -48,12 +54,13 @@ use failure::{err_msg, Error};
 
 use itertools::Itertools;
 use parse::parse_input;
+pub use parse::parse_packet;
 use std::{
     cmp::Ordering,
     fmt::{self, Display},
 };
 
-#[derive(Eq, PartialEq, Debug, Clone)]
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
 pub enum Packet {
     List(Box<[Packet]>),
     Number(u64),
@@ -78,10 +85,27 @@ impl Display for Packet {
     }
 }
 
-impl PartialOrd for Packet {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+impl Packet {
+    /// Rewrites the packet into a canonical form, so that packets which compare as equal are
+    /// structurally equal once normalized.
+    ///
+    /// A list holding only a number compares equal to that number, so these lists are replaced
+    /// by the number itself.  Any other list, including one holding only another list, is kept.
+    pub fn normalize(&self) -> Packet {
+        match self {
+            Packet::Number(x) => Packet::Number(*x),
+            Packet::List(xs) => match xs.iter().map(Packet::normalize).collect::<Vec<_>>()[..] {
+                [Packet::Number(x)] => Packet::Number(x),
+                ref normalized => Packet::List(normalized.into()),
+            },
+        }
+    }
+}
+
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
         use Packet::*;
-        Some(match (self, other) {
+        match (self, other) {
             (Number(x), Number(y)) => x.cmp(y),
             (List(x), List(y)) => x
                 .iter()
@@ -90,16 +114,45 @@ impl PartialOrd for Packet {
                 .then_with(|| x.len().cmp(&y.len())),
             (List(_), Number(y)) => self.cmp(&List(Box::new([Number(*y)]))),
             (Number(x), List(_)) => List(Box::new([Number(*x)])).cmp(other),
-        })
+        }
     }
 }
 
-impl Ord for Packet {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
+/// Compares two packets using the distress signal ordering rules.
+///
+/// ```
+/// use aoc2022::day13::{compare, parse_packet};
+/// use std::cmp::Ordering;
+///
+/// let a = parse_packet("[[1],[2,3,4]]").unwrap();
+/// let b = parse_packet("[[1],4]").unwrap();
+/// assert_eq!(compare(&a, &b), Ordering::Less);
+/// ```
+pub fn compare(a: &Packet, b: &Packet) -> Ordering {
+    a.cmp(b)
+}
+
+/// Whether two packets are equal under the distress signal ordering, even if they're written
+/// differently.
+///
+/// ```
+/// use aoc2022::day13::{equivalent, parse_packet};
+///
+/// let a = parse_packet("[[1],2]").unwrap();
+/// let b = parse_packet("[1,[2]]").unwrap();
+/// assert!(equivalent(&a, &b));
+/// assert_ne!(a, b);
+/// ```
+pub fn equivalent(a: &Packet, b: &Packet) -> bool {
+    compare(a, b) == Ordering::Equal
+}
+
 fn indices_of_ordered_pairs(pairs: &[(Packet, Packet)]) -> impl Iterator<Item = usize> + '_ {
     (1..)
         .zip(pairs.iter())
@@ -152,3 +205,97 @@ impl super::Solver for Solver {
         (Some(part_one), Some(part_two))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{equivalent, indices_of_ordered_pairs, parse_packet};
+    use crate::Solver;
+    use std::cmp::Ordering;
+
+    const SAMPLE: &str = "[1,1,3,1,1]
+[1,1,5,1,1]
+
+[[1],[2,3,4]]
+[[1],4]
+
+[9]
+[[8,7,6]]
+
+[[4,4],4,4]
+[[4,4],4,4,4]
+
+[7,7,7,7]
+[7,7,7]
+
+[]
+[3]
+
+[[[]]]
+[[]]
+
+[1,[2,[3,[4,[5,6,7]]]],8,9]
+[1,[2,[3,[4,[5,6,0]]]],8,9]
+";
+
+    #[test]
+    fn test_sample_orderings() {
+        let pairs = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let orderings = pairs.iter().map(|(x, y)| x.cmp(y)).collect::<Vec<_>>();
+        assert_eq!(
+            orderings,
+            vec![
+                Ordering::Less,
+                Ordering::Less,
+                Ordering::Greater,
+                Ordering::Less,
+                Ordering::Greater,
+                Ordering::Less,
+                Ordering::Greater,
+                Ordering::Greater,
+            ]
+        );
+        assert_eq!(
+            indices_of_ordered_pairs(&pairs).collect::<Vec<_>>(),
+            vec![1, 2, 4, 6]
+        );
+    }
+
+    #[test]
+    fn test_parse_whitespace() {
+        let pairs = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+
+        let trimmed = SAMPLE.trim_end().to_string();
+        assert_eq!(super::Solver::parse_input(trimmed).unwrap(), pairs);
+
+        let padded = format!("\n{}  \n\n", SAMPLE.replace('\n', " \r\n"));
+        assert_eq!(super::Solver::parse_input(padded).unwrap(), pairs);
+    }
+
+    #[test]
+    fn test_equivalent() {
+        let packet = |s| parse_packet(s).unwrap();
+
+        assert!(equivalent(&packet("[1,1,3]"), &packet("[1,1,3]")));
+        assert!(equivalent(&packet("1"), &packet("[1]")));
+        assert!(equivalent(&packet("[[[1]],[2]]"), &packet("[1,2]")));
+        assert!(!equivalent(&packet("[]"), &packet("[[]]")));
+        assert!(!equivalent(&packet("[1,1]"), &packet("[1]")));
+
+        assert_eq!(
+            packet("[[1],[[]],[[2,3]]]").normalize(),
+            packet("[1,[[]],[[2,3]]]")
+        );
+
+        let pairs = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let packets = pairs
+            .into_iter()
+            .flat_map(|(x, y)| [x.clone(), y.clone(), x.normalize(), y.normalize()])
+            .chain(["1", "[1]", "[[1]]", "[]", "[[]]", "[[[]]]"].map(packet))
+            .collect::<Vec<_>>();
+        for a in packets.iter() {
+            for b in packets.iter() {
+                assert_eq!(equivalent(a, b), a.normalize() == b.normalize());
+            }
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{common::Position, parsers::signed};
+use crate::{
+    common::{write_ppm, Bounds, NonEmptyBounds, Position},
+    parsers::signed,
+    render::render_grid,
+};
 use failure::{err_msg, Error};
 use itertools::{chain, Itertools};
 use nom::{
@@ -8,7 +12,12 @@ use nom::{
     multi::{many1, separated_list1},
     sequence::{separated_pair, terminated},
 };
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+pub const SOURCE: Position = Position { x: 500, y: 0 };
 
 fn parse_input(input: &str) -> Result<Box<[Path]>, Error> {
     let point = map(separated_pair(signed, tag(","), signed), Position::from);
@@ -42,10 +51,11 @@ impl Path {
     }
 }
 
-struct Contents {
+pub struct Contents {
     contents: HashMap<Position, Filler>,
     max_y: i64,
     floor: Option<i64>,
+    source: Position,
 }
 
 impl Contents {
@@ -55,6 +65,7 @@ impl Contents {
             contents: rocks,
             max_y,
             floor: floor_offset.map(|delta| max_y + delta),
+            source: SOURCE,
         }
     }
 
@@ -69,10 +80,33 @@ impl Contents {
     fn is_occupied(&self, position: Position) -> bool {
         self.contents.contains_key(&position) || Some(position.y) == self.floor
     }
+
+    pub fn filler_at(&self, position: Position) -> Option<Filler> {
+        self.contents
+            .get(&position)
+            .cloned()
+            .or_else(|| (Some(position.y) == self.floor).then_some(Filler::Rock))
+    }
+}
+
+impl Filler {
+    fn as_char(&self) -> char {
+        match self {
+            Filler::Rock => '#',
+            Filler::Sand => 'o',
+        }
+    }
+
+    fn colour(&self) -> [u8; 3] {
+        match self {
+            Filler::Rock => [128, 128, 128],
+            Filler::Sand => [230, 200, 90],
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum Filler {
+pub enum Filler {
     Rock,
     Sand,
 }
@@ -93,8 +127,8 @@ fn next_step(contents: &Contents, position: Position) -> Option<Position> {
         .find(|pos| !contents.is_occupied(*pos))
 }
 
-fn drop_grain(contents: &mut Contents, start_position: Position) -> Option<Position> {
-    let mut position = start_position;
+fn drop_grain(contents: &Contents, path: &mut Vec<Position>) -> Option<Position> {
+    let mut position = *path.last()?;
 
     while let Some(next_position) = next_step(contents, position) {
         position = next_position;
@@ -102,18 +136,27 @@ fn drop_grain(contents: &mut Contents, start_position: Position) -> Option<Posit
         if contents.is_out_of_bounds(position) {
             return None;
         }
+
+        path.push(position);
     }
 
     Some(position)
 }
 
-fn fill_sand(contents: &mut Contents) -> usize {
-    let start_position = Position { x: 500, y: 0 };
+fn fill_sand(contents: &mut Contents, source: Position) -> usize {
+    contents.source = source;
+
+    // Each grain follows the same route as the previous one up until the
+    // point where that grain came to rest, so resume from there rather than
+    // falling all the way from the source again.
+    let mut path = vec![source];
+
     for index in 0.. {
-        if contents.is_occupied(start_position) {
-            return index;
+        while matches!(path.last(), Some(&position) if contents.is_occupied(position)) {
+            path.pop();
         }
-        if let Some(position) = drop_grain(contents, start_position) {
+
+        if let Some(position) = drop_grain(contents, &mut path) {
             contents.add_grain(position);
         } else {
             return index;
@@ -122,9 +165,72 @@ fn fill_sand(contents: &mut Contents) -> usize {
     0
 }
 
-fn num_grains_to_stick(paths: &[Path], floor_offset: Option<i64>) -> usize {
+// The region containing the source, everything that's been placed and the floor.
+fn bounds(contents: &Contents) -> NonEmptyBounds {
+    let source = contents.source;
+    let bounds = Bounds::from(chain!(contents.contents.keys().cloned(), [source]).chain(
+        contents.floor.map(|floor| Position {
+            x: source.x,
+            y: floor,
+        }),
+    ));
+    *bounds.non_empty().unwrap()
+}
+
+pub fn render(contents: &Contents) -> String {
+    let source = contents.source;
+    let bounds = bounds(contents);
+
+    render_grid(&bounds.into(), |position| {
+        match contents.filler_at(position) {
+            Some(filler) => filler.as_char(),
+            None if position == source => '+',
+            None => '.',
+        }
+    })
+}
+
+/// Writes the cave as a PPM image with one pixel per position, drawing rock in grey, sand in
+/// yellow and air in black.
+pub fn render_ppm<W: Write>(contents: &Contents, writer: W) -> io::Result<()> {
+    let bounds = bounds(contents);
+    let (left, top) = (bounds.top_left.x, bounds.top_left.y);
+
+    write_ppm(
+        writer,
+        bounds.width() as usize,
+        bounds.height() as usize,
+        1,
+        |x, y| {
+            let position = Position {
+                x: left + x as i64,
+                y: top + y as i64,
+            };
+            contents
+                .filler_at(position)
+                .map(|filler| filler.colour())
+                .unwrap_or([0; 3])
+        },
+    )
+}
+
+#[allow(unused)]
+fn display(contents: &Contents) {
+    print!("{}", render(contents));
+}
+
+pub fn settle_sand(
+    paths: &[Path],
+    floor_offset: Option<i64>,
+    source: Position,
+) -> (Contents, usize) {
     let mut contents = draw_paths(paths, floor_offset);
-    fill_sand(&mut contents)
+    let num_grains = fill_sand(&mut contents, source);
+    (contents, num_grains)
+}
+
+pub fn num_grains_to_stick(paths: &[Path], floor_offset: Option<i64>, source: Position) -> usize {
+    settle_sand(paths, floor_offset, source).1
 }
 
 pub struct Solver {}
@@ -137,8 +243,106 @@ impl super::Solver for Solver {
     }
 
     fn solve(paths: Self::Problem) -> (Option<String>, Option<String>) {
-        let part_one = num_grains_to_stick(&paths, None).to_string();
-        let part_two = num_grains_to_stick(&paths, Some(2)).to_string();
+        let part_one = num_grains_to_stick(&paths, None, SOURCE).to_string();
+        let part_two = num_grains_to_stick(&paths, Some(2), SOURCE).to_string();
         (Some(part_one), Some(part_two))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{num_grains_to_stick, render, render_ppm, settle_sand, SOURCE};
+    use crate::{common::Position, Solver};
+
+    const SAMPLE: &str = "498,4 -> 498,6 -> 496,6
+503,4 -> 502,4 -> 502,9 -> 494,9
+";
+
+    #[test]
+    fn test_num_grains() {
+        let paths = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(num_grains_to_stick(&paths, None, SOURCE), 24);
+        assert_eq!(num_grains_to_stick(&paths, Some(2), SOURCE), 93);
+        assert_eq!(
+            super::Solver::solve(paths),
+            (Some("24".to_string()), Some("93".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_other_source() {
+        let paths = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+
+        // Everything falls straight down well to the left of the rocks.
+        let source = Position { x: 470, y: 0 };
+        assert_eq!(num_grains_to_stick(&paths, None, source), 0);
+
+        // With a floor the pile is a full triangle of height 11.
+        assert_eq!(num_grains_to_stick(&paths, Some(2), source), 121);
+
+        let (contents, _) = settle_sand(&paths, Some(2), source);
+        assert_eq!(
+            render(&contents)
+                .lines()
+                .next()
+                .unwrap()
+                .matches('o')
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let paths = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let (contents, _) = settle_sand(&paths, None, SOURCE);
+        assert_eq!(
+            render(&contents),
+            "......+...
+..........
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########.
+"
+        );
+
+        let (contents, num_grains) = settle_sand(&paths, Some(2), SOURCE);
+        let rendered = render(&contents);
+        assert_eq!(rendered.chars().filter(|&c| c == 'o').count(), 93);
+        assert_eq!(num_grains, 93);
+        assert!(rendered.lines().last().unwrap().chars().all(|c| c == '#'));
+    }
+
+    #[test]
+    fn test_render_ppm() {
+        let paths = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        for floor_offset in [None, Some(2)] {
+            let (contents, num_grains) = settle_sand(&paths, floor_offset, SOURCE);
+            let rendered = render(&contents);
+
+            let mut output = vec![];
+            render_ppm(&contents, &mut output).unwrap();
+            let (width, height) = (
+                rendered.lines().next().unwrap().len(),
+                rendered.lines().count(),
+            );
+            let header = format!("P6\n{} {}\n255\n", width, height);
+            assert_eq!(&output[..header.len()], header.as_bytes());
+
+            let pixels = output[header.len()..].chunks(3).collect::<Vec<_>>();
+            assert_eq!(pixels.len(), width * height);
+            let count = |colour: [u8; 3]| pixels.iter().filter(|&&pixel| pixel == colour).count();
+            assert_eq!(count([230, 200, 90]), num_grains);
+            assert_eq!(count([128, 128, 128]), rendered.matches('#').count());
+            assert_eq!(
+                count([0, 0, 0]),
+                width * height - rendered.matches(['#', 'o']).count()
+            );
+        }
+    }
+}
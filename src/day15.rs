@@ -1,4 +1,4 @@
-use crate::{common::Position, parsers::signed};
+use crate::{common::Position, parsers::signed, report};
 use failure::{err_msg, Error};
 use nom::{
     bytes::complete::tag,
@@ -55,12 +55,31 @@ pub struct Sensor {
 }
 
 impl Sensor {
+    fn radius(&self) -> u64 {
+        self.position.manhattan_distance_to(&self.beacon)
+    }
+
+    fn covers(&self, position: Position) -> bool {
+        self.position.manhattan_distance_to(&position) <= self.radius()
+    }
+
+    // All the positions just outside the area covered by the sensor.
+    fn perimeter(&self) -> impl Iterator<Item = Position> + '_ {
+        let distance = self.radius() as i64 + 1;
+        (0..=distance).flat_map(move |dx| {
+            let dy = distance - dx;
+            [(dx, dy), (dx, -dy), (-dx, dy), (-dx, -dy)]
+                .into_iter()
+                .map(move |delta| self.position + delta.into())
+        })
+    }
+
     fn empty_range_on_row(
         &self,
         y: i64,
         x_range: RangeInclusive<i64>,
     ) -> Option<RangeInclusive<i64>> {
-        let radius = self.position.manhattan_distance_to(&self.beacon) as i64;
+        let radius = self.radius() as i64;
         let dy = y.abs_diff(self.position.y) as i64;
         let min_x = self.position.x - radius + dy;
         let max_x = self.position.x + radius - dy;
@@ -86,12 +105,16 @@ fn count_beacons_on_row(sensors: &[Sensor], y: i64) -> usize {
         .len()
 }
 
-fn collapse_ranges(ranges: &mut Vec<RangeInclusive<i64>>) {
+// Merges overlapping ranges, and optionally ranges that touch end to end, so that the total
+// length of the ranges doesn't count any position twice.
+fn collapse_ranges(ranges: &mut Vec<RangeInclusive<i64>>, merge_adjacent: bool) {
     ranges.sort_by_key(|range| (*range.start(), *range.end()));
 
     let mut index = 0;
     while index + 1 < ranges.len() {
-        if ranges[index].contains(ranges[index + 1].start()) {
+        let next_start = *ranges[index + 1].start();
+        let touching = merge_adjacent && ranges[index].end().checked_add(1) == Some(next_start);
+        if ranges[index].contains(&next_start) || touching {
             ranges[index] =
                 *ranges[index].start()..=max(*ranges[index + 1].end(), *ranges[index].end());
             ranges.remove(index + 1);
@@ -101,7 +124,7 @@ fn collapse_ranges(ranges: &mut Vec<RangeInclusive<i64>>) {
     }
 }
 
-fn scanned_ranges_on_row(
+pub fn scanned_ranges_on_row(
     sensors: &[Sensor],
     y: i64,
     x_range: RangeInclusive<i64>,
@@ -111,44 +134,42 @@ fn scanned_ranges_on_row(
         .filter_map(move |sensor| sensor.empty_range_on_row(y, x_range.clone()))
 }
 
-fn count_empty_spaces_on_row(sensors: &[Sensor], y: i64) -> usize {
+/// The fewest ranges that exactly cover the positions on a row scanned by any sensor, in order.
+pub fn minimal_ranges_on_row(
+    sensors: &[Sensor],
+    y: i64,
+    x_range: RangeInclusive<i64>,
+) -> Vec<RangeInclusive<i64>> {
+    let mut ranges = scanned_ranges_on_row(sensors, y, x_range).collect::<Vec<_>>();
+    collapse_ranges(&mut ranges, true);
+    ranges
+}
+
+pub fn covered_length_on_row(sensors: &[Sensor], y: i64) -> i64 {
     let mut ranges = scanned_ranges_on_row(sensors, y, i64::MIN..=i64::MAX).collect::<Vec<_>>();
-    collapse_ranges(&mut ranges);
-    let num_beacons = count_beacons_on_row(sensors, y);
+    collapse_ranges(&mut ranges, false);
     ranges
         .iter()
         .map(|range| range.end() - range.start() + 1)
-        .sum::<i64>() as usize
-        - num_beacons
+        .sum()
 }
 
-fn empty_space_on_row(
-    sensors: &[Sensor],
-    y: i64,
-    x_range: RangeInclusive<i64>,
-) -> Option<Position> {
-    let mut ranges = scanned_ranges_on_row(sensors, y, x_range.clone()).collect::<Vec<_>>();
-    ranges.sort_by_key(|range| (*range.start(), *range.end()));
-    let mut next = *x_range.start();
-    for range in ranges {
-        if next < *range.start() {
-            return Some(Position { x: next, y });
-        } else if next <= *range.end() {
-            next = *range.end() + 1;
-        }
-        if next > *x_range.end() {
-            break;
-        }
-    }
-    None
+fn count_empty_spaces_on_row(sensors: &[Sensor], y: i64) -> usize {
+    covered_length_on_row(sensors, y) as usize - count_beacons_on_row(sensors, y)
 }
 
 fn find_beacon(
     sensors: &[Sensor],
     x_range: RangeInclusive<i64>,
-    mut y_range: RangeInclusive<i64>,
+    y_range: RangeInclusive<i64>,
 ) -> Option<Position> {
-    y_range.find_map(|y| empty_space_on_row(sensors, y, x_range.clone()))
+    // If there's only a single space that isn't covered by any sensor then it
+    // must lie just outside the area covered by at least one of them.
+    sensors
+        .iter()
+        .flat_map(|sensor| sensor.perimeter())
+        .filter(|position| x_range.contains(&position.x) && y_range.contains(&position.y))
+        .find(|&position| !sensors.iter().any(|sensor| sensor.covers(position)))
 }
 
 fn get_tuning_frequency(position: Position) -> i64 {
@@ -165,11 +186,94 @@ impl super::Solver for Solver {
     }
 
     fn solve(sensors: Self::Problem) -> (Option<String>, Option<String>) {
-        let part_one = count_empty_spaces_on_row(&sensors, 2_000_000).to_string();
-        let part_two = get_tuning_frequency(
-            find_beacon(&sensors, 0..=4000000, 0..=4000000).expect("Failed to solve part two"),
-        )
-        .to_string();
-        (Some(part_one), Some(part_two))
+        solve_with(&sensors, 2_000_000, 4_000_000)
+    }
+}
+
+pub fn solve_with(sensors: &[Sensor], row: i64, bound: i64) -> (Option<String>, Option<String>) {
+    let part_one = count_empty_spaces_on_row(sensors, row).to_string();
+    let part_two = find_beacon(sensors, 0..=bound, 0..=bound)
+        .map(get_tuning_frequency)
+        .ok_or_else(|| err_msg("No space found for the distress beacon"));
+    (Some(part_one), report(2, part_two))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        collapse_ranges, covered_length_on_row, find_beacon, minimal_ranges_on_row, solve_with,
+    };
+    use crate::common::Position;
+    use crate::Solver;
+
+    const SAMPLE: &str = "Sensor at x=2, y=18: closest beacon is at x=-2, y=15
+Sensor at x=9, y=16: closest beacon is at x=10, y=16
+Sensor at x=13, y=2: closest beacon is at x=15, y=3
+Sensor at x=12, y=14: closest beacon is at x=10, y=16
+Sensor at x=10, y=20: closest beacon is at x=10, y=16
+Sensor at x=14, y=17: closest beacon is at x=10, y=16
+Sensor at x=8, y=7: closest beacon is at x=2, y=10
+Sensor at x=2, y=0: closest beacon is at x=2, y=10
+Sensor at x=0, y=11: closest beacon is at x=2, y=10
+Sensor at x=20, y=14: closest beacon is at x=25, y=17
+Sensor at x=17, y=20: closest beacon is at x=21, y=22
+Sensor at x=16, y=7: closest beacon is at x=15, y=3
+Sensor at x=14, y=3: closest beacon is at x=15, y=3
+Sensor at x=20, y=1: closest beacon is at x=15, y=3
+";
+
+    #[test]
+    fn test_solve_sample() {
+        let sensors = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            solve_with(&sensors, 10, 20),
+            (Some("26".to_string()), Some("56000011".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_covered_length_on_row() {
+        let sensors = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(covered_length_on_row(&sensors, 10), 27);
+        assert_eq!(covered_length_on_row(&sensors, -100), 0);
+        assert_eq!(covered_length_on_row(&sensors, 11), 28);
+        assert_eq!(covered_length_on_row(&sensors, -10), 1);
+    }
+
+    #[test]
+    fn test_minimal_ranges_on_row() {
+        let sensors = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            minimal_ranges_on_row(&sensors, 10, -100..=100),
+            vec![-2..=24]
+        );
+        assert_eq!(
+            minimal_ranges_on_row(&sensors, 11, 0..=20),
+            vec![0..=13, 15..=20]
+        );
+        assert!(minimal_ranges_on_row(&sensors, -100, -100..=100).is_empty());
+    }
+
+    #[test]
+    fn test_find_beacon() {
+        let sensors = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            find_beacon(&sensors, 0..=20, 0..=20),
+            Some(Position { x: 14, y: 11 })
+        );
+        assert_eq!(find_beacon(&sensors, 0..=13, 0..=20), None);
+    }
+
+    #[test]
+    fn test_collapse_ranges() {
+        let ranges = vec![4..=6, 1..=3, 2..=2, 8..=9, 9..=12, i64::MAX..=i64::MAX];
+
+        let mut collapsed = ranges.clone();
+        collapse_ranges(&mut collapsed, false);
+        assert_eq!(collapsed, vec![1..=3, 4..=6, 8..=12, i64::MAX..=i64::MAX]);
+
+        let mut collapsed = ranges;
+        collapse_ranges(&mut collapsed, true);
+        assert_eq!(collapsed, vec![1..=6, 8..=12, i64::MAX..=i64::MAX]);
     }
 }
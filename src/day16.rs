@@ -1,5 +1,6 @@
-use crate::parsers::unsigned;
+use crate::{parsers::unsigned, report};
 use failure::{err_msg, Error};
+use itertools::Itertools;
 use nom::{
     branch::alt,
     bytes::complete::tag,
@@ -9,7 +10,12 @@ use nom::{
     sequence::{preceded, terminated, tuple},
     IResult,
 };
-use std::{array, cell::Cell, collections::HashMap};
+use std::{
+    array,
+    cell::Cell,
+    cmp::{max, Reverse},
+    collections::HashMap,
+};
 
 fn parse_input(input: &str) -> Result<Vec<Valve>, Error> {
     fn valve_name(input: &str) -> IResult<&str, String> {
@@ -47,6 +53,7 @@ fn parse_input(input: &str) -> Result<Vec<Valve>, Error> {
 enum Location<'a> {
     At(&'a Valve),
     EnRoute(&'a Valve, u64),
+    Idle(&'a Valve),
 }
 
 impl<'a> Location<'a> {
@@ -54,6 +61,7 @@ impl<'a> Location<'a> {
         match self {
             Location::At(valve) => valve,
             Self::EnRoute(valve, _) => valve,
+            Self::Idle(valve) => valve,
         }
     }
 
@@ -61,24 +69,31 @@ impl<'a> Location<'a> {
         match self {
             Location::At(_) => 0,
             Location::EnRoute(_, t) => *t,
+            Location::Idle(_) => u64::MAX,
         }
     }
 }
 
-struct Distances(HashMap<String, HashMap<String, u64>>);
+struct Distances {
+    indices: HashMap<String, usize>,
+    distances: Box<[Box<[u64]>]>,
+}
 
 impl Distances {
-    fn distance_between(&self, from: &Valve, to: &Valve) -> u64 {
-        *self.0.get(&from.name).unwrap().get(&to.name).unwrap()
+    // Unreachable valves are recorded as being u64::MAX away.
+    fn distance_between(&self, from: &Valve, to: &Valve) -> Option<u64> {
+        let distance = self.distances[self.indices[&from.name]][self.indices[&to.name]];
+        (distance != u64::MAX).then_some(distance)
     }
 
     fn min_distance(&self) -> u64 {
-        *self
-            .0
+        self.distances
             .iter()
-            .map(|(_, ds)| ds.values().filter(|dist| **dist > 0).min().unwrap())
+            .flat_map(|ds| ds.iter())
+            .filter(|dist| **dist > 0)
             .min()
-            .unwrap()
+            .cloned()
+            .unwrap_or(u64::MAX)
     }
 }
 
@@ -89,6 +104,8 @@ struct State<'a, const N: usize> {
     valves_remaining: Vec<&'a Valve>,
     pressure_released: u64,
     max_pressure: Cell<Option<u64>>,
+    // The valves opened so far and when, only kept when a schedule has been asked for.
+    history: Option<Vec<(u64, &'a Valve)>>,
 }
 
 fn all_location_combos<'a>(locations: &[Vec<Location<'a>>]) -> Vec<Vec<Location<'a>>> {
@@ -127,11 +144,16 @@ impl<'a, const N: usize> State<'a, N> {
             .iter()
             .map(|location| {
                 if let Location::At(loc) = location {
+                    // Allow the actor to stop, so that another actor can open
+                    // the last valves even if this one has nowhere left to go.
                     self.valves_remaining
                         .iter()
-                        .map(|valve| {
-                            Location::EnRoute(valve, distances.distance_between(loc, valve) + 1)
+                        .filter_map(|valve| {
+                            distances
+                                .distance_between(loc, valve)
+                                .map(|distance| Location::EnRoute(valve, distance + 1))
                         })
+                        .chain([Location::Idle(loc)])
                         .collect()
                 } else {
                     vec![location.clone()]
@@ -151,6 +173,7 @@ impl<'a, const N: usize> State<'a, N> {
                     let time_left = self.time_left - time_needed;
                     let locations = array::from_fn(|i| match next_locs[i] {
                         Location::At(_) => panic!("Should be en-route"),
+                        Location::Idle(valve) => Location::Idle(valve),
                         Location::EnRoute(to, t) => {
                             if t <= time_needed {
                                 Location::At(to)
@@ -179,12 +202,26 @@ impl<'a, const N: usize> State<'a, N> {
                         .iter()
                         .map(|name| valves.get(*name).unwrap().flow_rate)
                         .sum::<u64>();
+                    let history = self.history.as_ref().map(|history| {
+                        history
+                            .iter()
+                            .cloned()
+                            .chain(locations.iter().filter_map(|loc| {
+                                if let Location::At(valve) = loc {
+                                    Some((time_left, *valve))
+                                } else {
+                                    None
+                                }
+                            }))
+                            .collect()
+                    });
                     Some(State {
                         locations,
                         time_left,
                         valves_remaining,
                         pressure_released: self.pressure_released + time_left * flow_rate,
                         max_pressure: Cell::new(None),
+                        history,
                     })
                 } else {
                     None
@@ -202,7 +239,7 @@ impl<'a, const N: usize> State<'a, N> {
 
         let mut rem_valves: &[&Valve] = &self.valves_remaining;
 
-        while time > min_distance + 1 {
+        while time > min_distance.saturating_add(1) {
             for _ in self.locations.iter() {
                 if rem_valves.is_empty() {
                     break;
@@ -228,55 +265,86 @@ pub struct Valve {
     tunnels: Box<[String]>,
 }
 
+// The only valves worth travelling between are the start and those with a flow rate.
+fn include_valve(valve: &Valve) -> bool {
+    valve.name == "AA" || valve.flow_rate > 0
+}
+
 fn calculate_distances<F>(valves: &HashMap<String, Valve>, include_valve: F) -> Distances
 where
     F: Fn(&Valve) -> bool,
 {
-    let mut distances = HashMap::new();
-    for valve in valves.values() {
-        let mut distance = 1;
-        let mut next: Vec<_> = valve.tunnels.iter().collect();
-        let mut ds: HashMap<String, u64> = Default::default();
-        ds.insert(valve.name.clone(), 0);
-
-        while !next.is_empty() {
-            ds.extend(next.iter().map(|name| ((*name).clone(), distance)));
-            distance += 1;
-
-            next = next
-                .iter()
-                .flat_map(|name| valves.get(*name).unwrap().tunnels.iter())
-                .filter(|name| !ds.contains_key(*name))
-                .collect();
-        }
+    let names = valves.keys().sorted().collect::<Vec<_>>();
+    let indices: HashMap<&str, usize> = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.as_str(), index))
+        .collect();
 
-        distances.insert(valve.name.clone(), ds);
+    let mut distances = vec![vec![u64::MAX; names.len()]; names.len()];
+    for (index, name) in names.iter().enumerate() {
+        distances[index][index] = 0;
+        for tunnel in valves.get(*name).unwrap().tunnels.iter() {
+            distances[index][indices[tunnel.as_str()]] = 1;
+        }
     }
 
-    Distances(
-        distances
-            .into_iter()
-            .filter_map(|(name, ds)| {
-                if include_valve(valves.get(&name).unwrap()) {
-                    Some((
-                        name,
-                        ds.into_iter()
-                            .filter(|(name, _)| include_valve(valves.get(name).unwrap()))
-                            .collect(),
-                    ))
-                } else {
-                    None
+    for k in 0..names.len() {
+        for i in 0..names.len() {
+            for j in 0..names.len() {
+                let distance = distances[i][k].saturating_add(distances[k][j]);
+                if distance < distances[i][j] {
+                    distances[i][j] = distance;
                 }
-            })
+            }
+        }
+    }
+
+    let included = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| include_valve(valves.get(**name).unwrap()))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    Distances {
+        indices: included
+            .iter()
+            .enumerate()
+            .map(|(reduced, index)| (names[*index].clone(), reduced))
             .collect(),
-    )
+        distances: included
+            .iter()
+            .map(|&i| included.iter().map(|&j| distances[i][j]).collect())
+            .collect(),
+    }
 }
 
 fn find_most_pressure<const N: usize>(valves: &HashMap<String, Valve>, time_left: u64) -> u64 {
-    fn include_valve(valve: &Valve) -> bool {
-        valve.name == "AA" || valve.flow_rate > 0
-    }
+    search::<N>(valves, time_left, false).0
+}
 
+pub fn find_most_pressure_with_schedule<const N: usize>(
+    valves: &HashMap<String, Valve>,
+    time_left: u64,
+) -> (u64, Vec<(u64, String)>) {
+    let (best, history) = search::<N>(valves, time_left, true);
+    let schedule = history
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(time_remaining, valve)| (time_left - time_remaining, valve.name.clone()))
+        .collect();
+
+    (best, schedule)
+}
+
+// Finds the most pressure that can be released, along with the valves opened to release it if
+// `record_history` is set.
+fn search<const N: usize>(
+    valves: &HashMap<String, Valve>,
+    time_left: u64,
+    record_history: bool,
+) -> (u64, Option<Vec<(u64, &Valve)>>) {
     assert!(valves.get("AA").unwrap().flow_rate == 0);
 
     let distances = calculate_distances(valves, include_valve);
@@ -288,19 +356,23 @@ fn find_most_pressure<const N: usize>(valves: &HashMap<String, Valve>, time_left
         .collect();
     valves_by_flow_rate.sort_by(|a, b| a.flow_rate.cmp(&b.flow_rate).reverse());
 
+    let start = valves.get("AA").unwrap();
     let mut stack: Vec<State<N>> = vec![State {
-        locations: array::from_fn(|_| Location::At(valves.get("AA").unwrap())),
+        locations: array::from_fn(|_| Location::At(start)),
         time_left,
         valves_remaining: valves_by_flow_rate
             .iter()
             .filter(|valve| valve.flow_rate > 0)
+            .filter(|valve| distances.distance_between(start, valve).is_some())
             .cloned()
             .collect(),
         pressure_released: 0,
         max_pressure: Cell::new(None),
+        history: record_history.then(Vec::new),
     }];
 
     let mut best = 0;
+    let mut best_history = record_history.then(Vec::new);
     while let Some(state) = stack.pop() {
         if state.max_total_pressure(min_distance) <= best {
             continue;
@@ -308,6 +380,7 @@ fn find_most_pressure<const N: usize>(valves: &HashMap<String, Valve>, time_left
 
         if state.pressure_released > best {
             best = state.pressure_released;
+            best_history = state.history.clone();
         }
 
         let mut successors = state
@@ -319,7 +392,111 @@ fn find_most_pressure<const N: usize>(valves: &HashMap<String, Valve>, time_left
         stack.extend(successors);
     }
 
-    best
+    (best, best_history)
+}
+
+/// The most pressure a single actor can release in the time available for each set of valves
+/// that it could open, keyed by a bitmask of the valves opened.  The bits are assigned to the
+/// valves with a flow rate in order of their names.
+///
+/// Fails if more than 32 valves have a flow rate.
+pub fn best_pressure_by_subset(
+    valves: &HashMap<String, Valve>,
+    time: u64,
+) -> Result<HashMap<u32, u64>, Error> {
+    let distances = calculate_distances(valves, include_valve);
+    let start = valves.get("AA").unwrap();
+    let openable = valves
+        .values()
+        .filter(|valve| valve.flow_rate > 0)
+        .sorted_by_key(|valve| &valve.name)
+        .collect::<Vec<_>>();
+    if openable.len() > u32::BITS as usize {
+        return Err(err_msg(format!(
+            "Too many valves to track in a bitmask: {}",
+            openable.len()
+        )));
+    }
+
+    fn visit(
+        from: &Valve,
+        time_left: u64,
+        mask: u32,
+        pressure: u64,
+        openable: &[&Valve],
+        distances: &Distances,
+        best: &mut HashMap<u32, u64>,
+    ) {
+        let entry = best.entry(mask).or_insert(0);
+        *entry = max(*entry, pressure);
+
+        for (index, valve) in openable.iter().enumerate() {
+            if mask & (1 << index) != 0 {
+                continue;
+            }
+            if let Some(distance) = distances.distance_between(from, valve) {
+                // Opening the valve takes a minute once we've got there.
+                if distance + 1 < time_left {
+                    let time_left = time_left - distance - 1;
+                    visit(
+                        valve,
+                        time_left,
+                        mask | (1 << index),
+                        pressure + time_left * valve.flow_rate,
+                        openable,
+                        distances,
+                        best,
+                    );
+                }
+            }
+        }
+    }
+
+    let mut best = HashMap::new();
+    visit(start, time, 0, 0, &openable, &distances, &mut best);
+    Ok(best)
+}
+
+/// The most pressure two actors can release together, found by splitting the valves between
+/// them so that each opens a different set.
+pub fn find_most_pressure_pair(valves: &HashMap<String, Valve>, time: u64) -> Result<u64, Error> {
+    let mut subsets = best_pressure_by_subset(valves, time)?
+        .into_iter()
+        .collect::<Vec<_>>();
+    subsets.sort_by_key(|&(_, pressure)| Reverse(pressure));
+
+    let mut best = 0;
+    for (index, &(mask, pressure)) in subsets.iter().enumerate() {
+        // The other actor can't do better than the best remaining set, so once the total can't
+        // be beaten neither can any that follow.
+        if pressure * 2 <= best {
+            break;
+        }
+        for &(other_mask, other_pressure) in &subsets[index..] {
+            if pressure + other_pressure <= best {
+                break;
+            }
+            if mask & other_mask == 0 {
+                best = pressure + other_pressure;
+            }
+        }
+    }
+
+    Ok(best)
+}
+
+pub fn find_most_pressure_n(
+    valves: &HashMap<String, Valve>,
+    time_left: u64,
+    actors: usize,
+) -> Result<u64, Error> {
+    match actors {
+        1 => Ok(find_most_pressure::<1>(valves, time_left)),
+        2 => Ok(find_most_pressure::<2>(valves, time_left)),
+        3 => Ok(find_most_pressure::<3>(valves, time_left)),
+        4 => Ok(find_most_pressure::<4>(valves, time_left)),
+        _ => Err(err_msg(format!("Unsupported number of actors {}", actors))),
+    }
 }
 
 pub struct Solver {}
@@ -338,7 +515,195 @@ impl super::Solver for Solver {
 
     fn solve(valves: Self::Problem) -> (Option<String>, Option<String>) {
         let part_one = find_most_pressure::<1>(&valves, 30).to_string();
-        let part_two = find_most_pressure::<2>(&valves, 26).to_string();
-        (Some(part_one), Some(part_two))
+        let part_two = find_most_pressure_pair(&valves, 26);
+        (Some(part_one), report(2, part_two))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        best_pressure_by_subset, calculate_distances, find_most_pressure_n,
+        find_most_pressure_pair, find_most_pressure_with_schedule, include_valve, Valve,
+    };
+    use crate::Solver;
+    use itertools::Itertools;
+    use std::collections::{HashMap, VecDeque};
+
+    const SAMPLE: &str = "Valve AA has flow rate=0; tunnels lead to valves DD, II, BB
+Valve BB has flow rate=13; tunnels lead to valves CC, AA
+Valve CC has flow rate=2; tunnels lead to valves DD, BB
+Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE
+Valve EE has flow rate=3; tunnels lead to valves FF, DD
+Valve FF has flow rate=0; tunnels lead to valves EE, GG
+Valve GG has flow rate=0; tunnels lead to valves FF, HH
+Valve HH has flow rate=22; tunnel leads to valve GG
+Valve II has flow rate=0; tunnels lead to valves AA, JJ
+Valve JJ has flow rate=21; tunnel leads to valve II
+";
+
+    #[test]
+    fn test_find_most_pressure_n() {
+        let valves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(find_most_pressure_n(&valves, 30, 1).unwrap(), 1651);
+        assert_eq!(find_most_pressure_n(&valves, 26, 2).unwrap(), 1707);
+        assert_eq!(find_most_pressure_n(&valves, 26, 3).unwrap(), 1794);
+        assert_eq!(find_most_pressure_n(&valves, 26, 4).unwrap(), 1825);
+        assert!(find_most_pressure_n(&valves, 26, 0).is_err());
+        assert!(find_most_pressure_n(&valves, 26, 5).is_err());
+    }
+
+    #[test]
+    fn test_schedule() {
+        let valves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let (pressure, schedule) = find_most_pressure_with_schedule::<1>(&valves, 30);
+        assert_eq!(pressure, 1651);
+        assert_eq!(
+            schedule
+                .iter()
+                .map(|(minute, name)| (30 - minute) * valves[name].flow_rate)
+                .sum::<u64>(),
+            1651
+        );
+        assert_eq!(
+            schedule,
+            [
+                (2, "DD"),
+                (5, "BB"),
+                (9, "JJ"),
+                (17, "HH"),
+                (21, "EE"),
+                (24, "CC")
+            ]
+            .map(|(minute, name)| (minute, name.to_string()))
+        );
+
+        let (pressure, schedule) = find_most_pressure_with_schedule::<2>(&valves, 26);
+        assert_eq!(pressure, 1707);
+        assert_eq!(
+            schedule
+                .iter()
+                .map(|(minute, name)| (26 - minute) * valves[name].flow_rate)
+                .sum::<u64>(),
+            1707
+        );
+        assert_eq!(schedule.len(), 6);
+    }
+
+    #[test]
+    fn test_disconnected() {
+        let data = format!(
+            "{}Valve ZZ has flow rate=100; tunnels lead to valves YY
+Valve YY has flow rate=0; tunnels lead to valves ZZ
+",
+            SAMPLE
+        );
+        let valves = super::Solver::parse_input(data).unwrap();
+        assert_eq!(find_most_pressure_n(&valves, 30, 1).unwrap(), 1651);
+        assert_eq!(find_most_pressure_n(&valves, 26, 2).unwrap(), 1707);
+
+        let distances = calculate_distances(&valves, |valve| valve.flow_rate > 0);
+        assert_eq!(
+            distances.distance_between(&valves["ZZ"], &valves["BB"]),
+            None
+        );
+        assert_eq!(
+            distances.distance_between(&valves["ZZ"], &valves["ZZ"]),
+            Some(0)
+        );
+
+        let data = "Valve AA has flow rate=0; tunnels lead to valves BB
+Valve BB has flow rate=0; tunnels lead to valves AA
+Valve CC has flow rate=10; tunnels lead to valves DD
+Valve DD has flow rate=5; tunnels lead to valves CC
+";
+        let valves = super::Solver::parse_input(data.to_string()).unwrap();
+        assert_eq!(find_most_pressure_n(&valves, 30, 1).unwrap(), 0);
+        assert_eq!(
+            super::Solver::solve(valves),
+            (Some("0".to_string()), Some("0".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_best_pressure_by_subset() {
+        let valves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let best = best_pressure_by_subset(&valves, 30).unwrap();
+        assert_eq!(best[&0], 0);
+        // Valves BB, CC, DD, EE, HH and JJ get bits 0 to 5.
+        assert_eq!(best[&0b111111], 1651);
+        assert_eq!(best.values().max(), Some(&1651));
+        // Only DD opened, arriving after one minute and opening it in the next.
+        assert_eq!(best[&0b000100], 28 * 20);
+
+        assert_eq!(find_most_pressure_pair(&valves, 26).unwrap(), 1707);
+        assert_eq!(
+            find_most_pressure_pair(&valves, 26).unwrap(),
+            find_most_pressure_n(&valves, 26, 2).unwrap()
+        );
+
+        // A line of valves leading away from AA, with too many to fit in the bitmask.
+        let names = ["AA".to_string()]
+            .into_iter()
+            .chain((0..33).map(|index| {
+                format!(
+                    "B{}{}",
+                    (b'A' + index / 26) as char,
+                    (b'A' + index % 26) as char
+                )
+            }))
+            .collect::<Vec<_>>();
+        let data = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let tunnels = [index.checked_sub(1), Some(index + 1)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|index| names.get(index))
+                    .join(", ");
+                let flow_rate = if index == 0 { 0 } else { 1 };
+                format!(
+                    "Valve {} has flow rate={}; tunnels lead to valves {}\n",
+                    name, flow_rate, tunnels
+                )
+            })
+            .collect::<String>();
+        let valves = super::Solver::parse_input(data).unwrap();
+        assert!(best_pressure_by_subset(&valves, 26).is_err());
+        assert!(find_most_pressure_pair(&valves, 26).is_err());
+    }
+
+    fn bfs_distances(valves: &HashMap<String, Valve>, from: &str) -> HashMap<String, u64> {
+        let mut distances = HashMap::from([(from.to_string(), 0)]);
+        let mut queue = VecDeque::from([from.to_string()]);
+        while let Some(name) = queue.pop_front() {
+            let distance = distances[&name];
+            for tunnel in valves[&name].tunnels.iter() {
+                if !distances.contains_key(tunnel) {
+                    distances.insert(tunnel.clone(), distance + 1);
+                    queue.push_back(tunnel.clone());
+                }
+            }
+        }
+        distances
+    }
+
+    #[test]
+    fn test_distances() {
+        let valves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let distances = calculate_distances(&valves, include_valve);
+
+        assert_eq!(distances.indices.len(), 7);
+        for from in valves.values().filter(|valve| include_valve(valve)) {
+            let expected = bfs_distances(&valves, &from.name);
+            for to in valves.values().filter(|valve| include_valve(valve)) {
+                assert_eq!(
+                    distances.distance_between(from, to),
+                    expected.get(&to.name).cloned()
+                );
+            }
+        }
+        assert_eq!(distances.min_distance(), 1);
     }
 }
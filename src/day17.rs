@@ -3,6 +3,7 @@ use std::{
     cmp::{max, min},
     collections::HashMap,
     collections::HashSet,
+    io::{self, stdout, Write},
     ops::Range,
     str::FromStr,
 };
@@ -37,8 +38,8 @@ impl TryFrom<char> for Direction {
     }
 }
 
-#[derive(Debug)]
-struct Rock {
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rock {
     offsets: Vec<Position>,
     width: i64,
     height: i64,
@@ -54,6 +55,10 @@ impl FromStr for Rock {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(c) = s.chars().find(|c| !"#.\n".contains(*c)) {
+            return Err(err_msg(format!("Invalid character {:?} in rock", c)));
+        }
+
         let offsets: Vec<Position> = s
             .lines()
             .rev()
@@ -71,7 +76,12 @@ impl FromStr for Rock {
                 })
             })
             .collect();
-        let width = offsets.iter().map(|pos| pos.x).max().unwrap() + 1;
+        let width = offsets
+            .iter()
+            .map(|pos| pos.x)
+            .max()
+            .ok_or_else(|| err_msg("Rock has no parts"))?
+            + 1;
         let height = offsets.iter().map(|pos| pos.y).max().unwrap() + 1;
 
         Ok(Rock {
@@ -135,6 +145,15 @@ impl Tower {
 
     #[allow(unused)]
     fn draw(&self, rock: Option<(&Rock, Position)>, rows: usize) {
+        self.write(stdout(), rock, rows).unwrap()
+    }
+
+    fn write<W: Write>(
+        &self,
+        mut writer: W,
+        rock: Option<(&Rock, Position)>,
+        rows: usize,
+    ) -> io::Result<()> {
         let rock_positions = if let Some((rock, position)) = rock {
             rock.positions_at(position).collect::<HashSet<_>>()
         } else {
@@ -142,43 +161,71 @@ impl Tower {
         };
 
         for y in (0..=self.max_y + 4).rev().take(rows) {
-            print!("|");
+            write!(writer, "|")?;
             for x in 0..self.width {
                 let position = (x, y).into();
                 if rock_positions.contains(&position) {
-                    print!("@");
+                    write!(writer, "@")?;
                 } else if self.filled.contains_key(&position) {
-                    print!("#");
+                    write!(writer, "#")?;
                 } else {
-                    print!(".");
+                    write!(writer, ".")?;
                 }
             }
-            println!("|");
+            writeln!(writer, "|")?;
         }
         if self.height() as usize > rows {
-            println!("...\n\n");
+            writeln!(writer, "...\n\n")
         } else {
-            print!("+");
+            write!(writer, "+")?;
             for _ in 0..self.width {
-                print!("-");
+                write!(writer, "-")?;
             }
-            print!("+\n\n")
+            write!(writer, "+\n\n")
         }
     }
 }
 
-fn get_rocks() -> Box<[Rock]> {
-    [
-        "####",
-        ".#.\n###\n.#.",
-        "..#\n..#\n###",
-        "#\n#\n#\n#",
-        "##\n##",
-    ]
-    .into_iter()
-    .map(|rock| rock.parse().unwrap())
-    .collect::<Vec<_>>()
-    .into_boxed_slice()
+pub const DEFAULT_ROCKS: &str = "####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##
+";
+
+/// Parses a set of rocks, drawn with '#' and '.' and separated by blank lines.
+pub fn parse_rocks(spec: &str) -> Result<Box<[Rock]>, Error> {
+    spec.trim_end()
+        .split("\n\n")
+        .map(|rock| {
+            let rock: Rock = rock.parse()?;
+            // Rocks appear two units away from the left wall.
+            if rock.width > TOWER_WIDTH - 2 {
+                return Err(err_msg(format!(
+                    "Rock of width {} doesn't fit in the chamber",
+                    rock.width
+                )));
+            }
+            Ok(rock)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Vec::into_boxed_slice)
+}
+
+pub fn get_rocks() -> Box<[Rock]> {
+    parse_rocks(DEFAULT_ROCKS).unwrap()
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -332,16 +379,16 @@ impl Draw {
 }
 
 #[derive(Debug, PartialEq, Eq)]
-struct Segment {
+pub struct Segment {
     height_deltas: Vec<i64>,
 }
 
 impl Segment {
-    fn total_height(&self) -> i64 {
+    pub fn total_height(&self) -> i64 {
         *self.height_deltas.last().unwrap()
     }
 
-    fn height_after_rocks(&self, num_rocks: usize) -> i64 {
+    pub fn height_after_rocks(&self, num_rocks: usize) -> i64 {
         if num_rocks > 0 {
             self.height_deltas[num_rocks - 1]
         } else {
@@ -349,12 +396,12 @@ impl Segment {
         }
     }
 
-    fn cycle_height_after_rocks(&self, num_rocks: usize) -> i64 {
+    pub fn cycle_height_after_rocks(&self, num_rocks: usize) -> i64 {
         (num_rocks / self.num_rocks()) as i64 * self.total_height()
             + self.height_after_rocks(num_rocks % self.num_rocks())
     }
 
-    fn num_rocks(&self) -> usize {
+    pub fn num_rocks(&self) -> usize {
         self.height_deltas.len()
     }
 }
@@ -460,7 +507,7 @@ impl Watcher for CycleFinder {
     }
 }
 
-fn find_prefix_and_cycle_time(jets: &[Direction], rocks: &[Rock]) -> (Segment, Segment) {
+pub fn find_prefix_and_cycle_time(jets: &[Direction], rocks: &[Rock]) -> (Segment, Segment) {
     drop_rocks(
         rocks.iter().cycle(),
         jets.iter().cloned().cycle(),
@@ -469,7 +516,13 @@ fn find_prefix_and_cycle_time(jets: &[Direction], rocks: &[Rock]) -> (Segment, S
     )
 }
 
-fn find_height_after(rocks: &[Rock], jets: &[Direction], num_rocks: usize) -> i64 {
+/// Finds the height of the tower once the given number of rocks have fallen.
+///
+/// Finding the repeating pattern relies on rocks regularly coming to rest such that no later
+/// rock reaches below them, which happens with the default rocks. Other sets of rocks might not
+/// do this, e.g. if narrow rocks keep slipping down gaps beside wider ones, in which case this
+/// never returns.
+pub fn find_height_after(rocks: &[Rock], jets: &[Direction], num_rocks: usize) -> i64 {
     let (prefix, cycle) = find_prefix_and_cycle_time(jets, rocks);
 
     let prefix_rocks = min(prefix.num_rocks(), num_rocks);
@@ -478,6 +531,22 @@ fn find_height_after(rocks: &[Rock], jets: &[Direction], num_rocks: usize) -> i6
     prefix.height_after_rocks(prefix_rocks) + cycle.cycle_height_after_rocks(cycle_rocks)
 }
 
+/// Describes how the tower grows as `(prefix_rocks, prefix_height, cycle_rocks, cycle_height)`.
+///
+/// Once the first `prefix_rocks` rocks have fallen, reaching a height of `prefix_height`, the
+/// tower repeats itself every `cycle_rocks` rocks, growing by `cycle_height` each time.  The
+/// height after a huge number of rocks is therefore the prefix height, plus a whole number of
+/// cycles, plus the height gained from however many rocks are left over at the start of a cycle.
+pub fn height_formula(rocks: &[Rock], jets: &[Direction]) -> (usize, i64, usize, i64) {
+    let (prefix, cycle) = find_prefix_and_cycle_time(jets, rocks);
+    (
+        prefix.num_rocks(),
+        prefix.total_height(),
+        cycle.num_rocks(),
+        cycle.total_height(),
+    )
+}
+
 pub struct Solver {}
 
 impl super::Solver for Solver {
@@ -499,3 +568,131 @@ impl super::Solver for Solver {
         (Some(part_one), Some(part_two))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        drop_rock, drop_rocks, find_height_after, get_rocks, height_formula, parse_rocks, Draw,
+        GetHeightAfter, Rock, Tower, DEFAULT_ROCKS, TOWER_WIDTH,
+    };
+    use crate::{common::Position, Solver};
+
+    const SAMPLE: &str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+
+    #[test]
+    fn test_parse_rocks() {
+        let rocks = parse_rocks(DEFAULT_ROCKS).unwrap();
+        assert_eq!(
+            rocks
+                .iter()
+                .map(|rock| (rock.width, rock.height, rock.offsets.len()))
+                .collect::<Vec<_>>(),
+            [(4, 1, 4), (3, 3, 5), (3, 3, 5), (1, 4, 4), (2, 2, 4)]
+        );
+        assert_eq!(rocks, get_rocks());
+        assert_eq!(rocks[2], "..#\n..#\n###".parse::<Rock>().unwrap());
+
+        let rocks = parse_rocks("###\n\n##\n##\n").unwrap();
+        assert_eq!(rocks.len(), 2);
+        let jets = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let simulated = drop_rocks(
+            rocks.iter().cycle(),
+            jets.iter().cloned().cycle(),
+            GetHeightAfter::new(2022),
+            Draw::Never,
+        );
+        assert_eq!(find_height_after(&rocks, &jets, 2022), simulated);
+
+        assert!(parse_rocks("").is_err());
+        assert!(parse_rocks("#x#").is_err());
+        assert!(parse_rocks("######").is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let jets = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let rocks = get_rocks();
+        let mut jets = jets.iter().cloned().cycle();
+        let mut tower = Tower::new(TOWER_WIDTH);
+
+        for (index, rock) in rocks.iter().take(3).enumerate() {
+            let from = Position {
+                x: 2,
+                y: tower.max_y + 4,
+            };
+            let (_, fallen_rock) = drop_rock(rock, &mut jets, &tower, from);
+            tower.add_rock(rock, fallen_rock.position, index + 1);
+        }
+
+        let mut output = vec![];
+        tower.write(&mut output, None, 20).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "|.......|
+|.......|
+|.......|
+|.......|
+|..#....|
+|..#....|
+|####...|
+|..###..|
+|...#...|
+|..####.|
++-------+
+
+"
+        );
+
+        let next = Position {
+            x: 2,
+            y: tower.max_y + 4,
+        };
+        let mut output = vec![];
+        tower
+            .write(&mut output, Some((&rocks[3], next)), 3)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "|..@....|
+|.......|
+|.......|
+...
+
+
+"
+        );
+    }
+
+    #[test]
+    fn test_height_formula() {
+        let jets = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let rocks = get_rocks();
+
+        let (prefix_rocks, prefix_height, cycle_rocks, cycle_height) =
+            height_formula(&rocks, &jets);
+        assert_eq!(
+            (prefix_rocks, prefix_height, cycle_rocks, cycle_height),
+            (16, 26, 35, 53)
+        );
+        assert_eq!(
+            find_height_after(&rocks, &jets, prefix_rocks + 3 * cycle_rocks),
+            prefix_height + 3 * cycle_height
+        );
+
+        for num_rocks in [1, 10, 100, 2022] {
+            let simulated = drop_rocks(
+                rocks.iter().cycle(),
+                jets.iter().cloned().cycle(),
+                GetHeightAfter::new(num_rocks),
+                Draw::Never,
+            );
+            assert_eq!(find_height_after(&rocks, &jets, num_rocks), simulated);
+        }
+
+        assert_eq!(find_height_after(&rocks, &jets, 2022), 3068);
+        assert_eq!(
+            find_height_after(&rocks, &jets, 1000000000000),
+            1514285714288
+        );
+    }
+}
@@ -1,7 +1,15 @@
-use std::{array, collections::HashSet, ops::RangeInclusive};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+    ops::RangeInclusive,
+};
 
-use crate::{common::Vector, parsers::signed};
+use crate::{
+    common::{flood_fill, Vector},
+    parsers::signed,
+};
 use failure::{err_msg, Error};
+use itertools::Itertools;
 use nom::{
     bytes::complete::tag,
     character::complete::newline,
@@ -21,7 +29,7 @@ fn parse_input(data: &str) -> Result<Box<[Vector<i64, 3>]>, Error> {
         .map_err(|err| err_msg(format!("Failed to parse vectors: {}", err)))
 }
 
-fn find_total_surface_area<'a, T: Iterator<Item = &'a Vector<i64, 3>> + Clone>(
+fn find_total_surface_area<'a, const D: usize, T: Iterator<Item = &'a Vector<i64, D>> + Clone>(
     positions: T,
 ) -> usize {
     let occupied = positions.clone().cloned().collect::<HashSet<_>>();
@@ -32,48 +40,97 @@ fn find_total_surface_area<'a, T: Iterator<Item = &'a Vector<i64, 3>> + Clone>(
         .count()
 }
 
-fn find_dimensions(positions: &[Vector<i64, 3>]) -> Vector<RangeInclusive<i64>, 3> {
-    array::from_fn(|axis| {
-        let min = positions.iter().map(|pos| pos[axis]).min().unwrap();
-        let max = positions.iter().map(|pos| pos[axis]).max().unwrap();
-        min..=max
-    })
-    .into()
-}
+fn find_outside<const D: usize>(
+    positions: &[Vector<i64, D>],
+) -> (Vector<RangeInclusive<i64>, D>, HashSet<Vector<i64, D>>) {
+    let scan_ranges = Vector::bounding(positions).unwrap().expand(1);
 
-fn surface_area_of_box(ranges: Vector<RangeInclusive<i64>, 3>) -> usize {
-    let dimensions: [usize; 3] =
-        array::from_fn(|i| (ranges[i].end() - ranges[i].start() + 1) as usize);
+    let occupied = positions.iter().collect::<HashSet<_>>();
 
-    2 * (dimensions[0] * dimensions[1]
-        + dimensions[0] * dimensions[2]
-        + dimensions[1] * dimensions[2])
+    let outside = flood_fill(scan_ranges.start(), |position| {
+        position
+            .adjacent()
+            .filter(|adjacent| scan_ranges.contains(adjacent) && !occupied.contains(adjacent))
+            .collect::<Vec<_>>()
+    });
+
+    (scan_ranges, outside)
 }
 
-fn find_external_surface_area(positions: &[Vector<i64, 3>]) -> usize {
-    let dimensions = find_dimensions(positions);
-    let scan_ranges: Vector<_, 3> =
-        array::from_fn(|axis| dimensions[axis].start() - 1..=dimensions[axis].end() + 1).into();
+fn find_external_surface_area<const D: usize>(positions: &[Vector<i64, D>]) -> usize {
+    let (scan_ranges, outside) = find_outside(positions);
+    find_total_surface_area(outside.iter()) - scan_ranges.surface_area()
+}
 
-    let occupied = positions.iter().collect::<HashSet<_>>();
+pub fn trapped_air_cells<const D: usize>(positions: &[Vector<i64, D>]) -> usize {
+    let (scan_ranges, outside) = find_outside(positions);
+    let num_occupied = positions.iter().collect::<HashSet<_>>().len();
+    scan_ranges.volume() - outside.len() - num_occupied
+}
 
-    let start: Vector<i64, 3> = array::from_fn(|axis| *scan_ranges[axis].start()).into();
-    let mut to_check: Vec<Vector<i64, 3>> = vec![start.clone()];
-    let mut found = HashSet::from([start]);
-
-    while let Some(position) = to_check.pop() {
-        for adjacent in position.adjacent() {
-            if scan_ranges.contains(&adjacent)
-                && !found.contains(&adjacent)
-                && !occupied.contains(&adjacent)
-            {
-                found.insert(adjacent.clone());
-                to_check.push(adjacent);
+/// Writes the droplet as a Wavefront OBJ mesh made of a unit cube for each occupied cell.
+///
+/// With `external_only` set, only the faces that can be reached from outside the droplet are
+/// written, leaving out faces shared between cells and those facing trapped air.
+pub fn to_obj<W: Write>(
+    positions: &[Vector<i64, 3>],
+    external_only: bool,
+    mut writer: W,
+) -> io::Result<()> {
+    let occupied = positions.iter().collect::<HashSet<_>>();
+    let outside = if external_only && !positions.is_empty() {
+        Some(find_outside(positions).1)
+    } else {
+        None
+    };
+
+    let mut vertices: Vec<[i64; 3]> = vec![];
+    let mut vertex_indices: HashMap<[i64; 3], usize> = HashMap::new();
+    let mut faces = vec![];
+
+    for position in positions.iter().unique() {
+        for axis in 0..3 {
+            for side in [0, 1] {
+                let mut adjacent = [position[0], position[1], position[2]];
+                adjacent[axis] += if side == 1 { 1 } else { -1 };
+                let adjacent = Vector::from(adjacent);
+
+                if let Some(outside) = &outside {
+                    if occupied.contains(&adjacent) || !outside.contains(&adjacent) {
+                        continue;
+                    }
+                }
+
+                // Walk around the face so that its normal points out of the cube.
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+                if side == 0 {
+                    corners.reverse();
+                }
+
+                let face = corners.map(|(du, dv)| {
+                    let mut vertex = [position[0], position[1], position[2]];
+                    vertex[axis] += side;
+                    vertex[u] += du;
+                    vertex[v] += dv;
+                    *vertex_indices.entry(vertex).or_insert_with(|| {
+                        vertices.push(vertex);
+                        vertices.len()
+                    })
+                });
+                faces.push(face);
             }
         }
     }
 
-    find_total_surface_area(found.iter()) - surface_area_of_box(scan_ranges)
+    for [x, y, z] in vertices {
+        writeln!(writer, "v {} {} {}", x, y, z)?;
+    }
+    for [a, b, c, d] in faces {
+        writeln!(writer, "f {} {} {} {}", a, b, c, d)?;
+    }
+
+    Ok(())
 }
 
 pub struct Solver {}
@@ -91,3 +148,97 @@ impl super::Solver for Solver {
         (Some(part_one), Some(part_two))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{find_external_surface_area, find_total_surface_area, to_obj, trapped_air_cells};
+    use crate::{common::Vector, Solver};
+
+    const SAMPLE: &str = "2,2,2
+1,2,2
+3,2,2
+2,1,2
+2,3,2
+2,2,1
+2,2,3
+2,2,4
+2,2,6
+1,2,5
+3,2,5
+2,1,5
+2,3,5
+";
+
+    #[test]
+    fn test_surface_area() {
+        let positions = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(find_total_surface_area(positions.iter()), 64);
+        assert_eq!(find_external_surface_area(&positions), 58);
+        assert_eq!(trapped_air_cells(&positions), 1);
+    }
+
+    fn obj_counts(positions: &[Vector<i64, 3>], external_only: bool) -> (usize, usize) {
+        let mut output = vec![];
+        to_obj(positions, external_only, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        (
+            output.lines().filter(|line| line.starts_with("v ")).count(),
+            output.lines().filter(|line| line.starts_with("f ")).count(),
+        )
+    }
+
+    #[test]
+    fn test_to_obj() {
+        let cube = [[0, 0, 0].into()];
+        let mut output = vec![];
+        to_obj(&cube, false, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(obj_counts(&cube, false), (8, 6));
+        assert_eq!(obj_counts(&cube, true), (8, 6));
+
+        // Every vertex is used by three faces.
+        let mut uses = [0; 8];
+        for line in output.lines().filter(|line| line.starts_with("f ")) {
+            for index in line.split(' ').skip(1) {
+                uses[index.parse::<usize>().unwrap() - 1] += 1;
+            }
+        }
+        assert_eq!(uses, [3; 8]);
+
+        let positions = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(obj_counts(&positions, false).1, 6 * positions.len());
+        assert_eq!(obj_counts(&positions, true).1, 58);
+        assert_eq!(obj_counts(&[], true), (0, 0));
+    }
+
+    #[test]
+    fn test_hollow_cube() {
+        let is_shell = |pos: &[i64; 3]| pos.iter().any(|&d| d == 0 || d == 3);
+        let cube: Vec<Vector<i64, 3>> = (0..4)
+            .flat_map(|x| (0..4).flat_map(move |y| (0..4).map(move |z| [x, y, z])))
+            .filter(is_shell)
+            .map(Vector::from)
+            .collect();
+
+        assert_eq!(trapped_air_cells(&cube), 8);
+        assert_eq!(find_external_surface_area(&cube), 6 * 16);
+        assert_eq!(find_total_surface_area(cube.iter()), 6 * 16 + 6 * 4);
+    }
+
+    #[test]
+    fn test_perimeter() {
+        // A ring of squares around a single hole.
+        let ring: Vec<Vector<i64, 2>> = (0..3)
+            .flat_map(|x| (0..3).map(move |y| [x, y]))
+            .filter(|&pos| pos != [1, 1])
+            .map(Vector::from)
+            .collect();
+
+        assert_eq!(find_total_surface_area(ring.iter()), 16);
+        assert_eq!(find_external_surface_area(&ring), 12);
+
+        let line: Vec<Vector<i64, 2>> = (0..4).map(|x| [x, 7].into()).collect();
+        assert_eq!(find_total_surface_area(line.iter()), 10);
+        assert_eq!(find_external_surface_area(&line), 10);
+    }
+}
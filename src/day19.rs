@@ -240,6 +240,7 @@ struct State<'a> {
     minutes_remaining: u64,
     resources: ResourceArray<u64>,
     num_robots: ResourceArray<u64>,
+    #[cfg(feature = "trace")]
     history: Vec<(u64, Resource)>,
 }
 
@@ -267,6 +268,7 @@ impl<'a> State<'a> {
             minutes_remaining,
             resources: ResourceArray::default(),
             num_robots,
+            #[cfg(feature = "trace")]
             history: vec![],
         }
     }
@@ -317,6 +319,7 @@ impl<'a> State<'a> {
         state = state.advance(1)?;
         state.num_robots[robot_type] += 1;
 
+        #[cfg(feature = "trace")]
         state.history.push((state.minutes_passed, robot_type));
 
         Some(state)
@@ -334,6 +337,7 @@ impl<'a> State<'a> {
                 minutes_remaining: self.minutes_remaining - minutes,
                 resources,
                 num_robots: self.num_robots,
+                #[cfg(feature = "trace")]
                 history: self.history.clone(),
             })
         } else {
@@ -344,15 +348,35 @@ impl<'a> State<'a> {
     fn projected_resource_amount(&self, resource: Resource, minutes: u64) -> u64 {
         self.resources[resource] + minutes * self.num_robots[resource]
     }
+
+    // The most geodes we could possibly end up with, assuming that we can build a new geode
+    // robot every remaining minute.
+    fn max_possible_geodes(&self) -> u64 {
+        let minutes = self.minutes_remaining;
+        self.projected_resource_amount(Resource::Geode, minutes)
+            + minutes * minutes.saturating_sub(1) / 2
+    }
 }
 
-fn find_max_geodes(blueprint: &Blueprint, minutes: u64) -> u64 {
-    println!("Checking blueprint {}", blueprint.index);
+pub fn find_max_geodes(blueprint: &Blueprint, minutes: u64) -> u64 {
+    #[cfg(feature = "trace")]
+    eprintln!("Checking blueprint {}", blueprint.index);
     let mut stack = vec![State::new(blueprint, minutes)];
 
     let mut max_geodes = 0;
 
     while let Some(state) = stack.pop() {
+        if state.max_possible_geodes() <= max_geodes {
+            continue;
+        }
+
+        // Whatever else happens we'll end up with at least as many geodes as we would by
+        // building nothing more.
+        max_geodes = max(
+            state.projected_resource_amount(Resource::Geode, state.minutes_remaining),
+            max_geodes,
+        );
+
         let possible_robot_types = Resource::all()
             // We can only build one robot per minute, so if the most a single robot can cost
             // of a resource is X, then there's no point building more than X of that robot.
@@ -379,20 +403,17 @@ fn find_max_geodes(blueprint: &Blueprint, minutes: u64) -> u64 {
             })
             .collect::<Vec<_>>();
 
-        if next_states.is_empty() {
-            max_geodes = max(
-                state.projected_resource_amount(Resource::Geode, state.minutes_remaining),
-                max_geodes,
-            );
-        } else {
-            stack.extend(next_states);
-        }
+        stack.extend(
+            next_states
+                .into_iter()
+                .filter(|next_state| next_state.max_possible_geodes() > max_geodes),
+        );
     }
 
     max_geodes
 }
 
-fn get_quality(blueprint: &Blueprint, minutes: u64) -> u64 {
+pub fn get_quality(blueprint: &Blueprint, minutes: u64) -> u64 {
     blueprint.index * find_max_geodes(blueprint, minutes)
 }
 
@@ -426,6 +447,35 @@ impl super::Solver for Solver {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::Solver;
+
+    const SAMPLE: &str = "Blueprint 1: \
+Each ore robot costs 4 ore. \
+Each clay robot costs 2 ore. \
+Each obsidian robot costs 3 ore and 14 clay. \
+Each geode robot costs 2 ore and 7 obsidian.
+Blueprint 2: \
+Each ore robot costs 2 ore. \
+Each clay robot costs 3 ore. \
+Each obsidian robot costs 3 ore and 8 clay. \
+Each geode robot costs 3 ore and 12 obsidian.
+";
+
+    #[test]
+    fn test_find_max_geodes() {
+        let blueprints = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(find_max_geodes(&blueprints[0], 24), 9);
+        assert_eq!(get_quality(&blueprints[0], 24), 9);
+        assert_eq!(get_quality(&blueprints[1], 10), 0);
+    }
+
+    #[test]
+    fn test_solve() {
+        let blueprints = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(total_quality(&blueprints, 24), 33);
+        assert_eq!(find_max_geodes(&blueprints[0], 32), 56);
+        assert_eq!(find_max_geodes(&blueprints[1], 32), 62);
+    }
 
     #[test]
     fn test_order_resource() {
@@ -1,14 +1,15 @@
 use std::{
-    cmp::Ordering,
-    fmt::Display,
+    fmt::{Debug, Display},
     iter::repeat,
     num::ParseIntError,
     ops::{Index, IndexMut},
 };
 
-use failure::Error;
+use failure::{err_msg, Error};
 use itertools::Itertools;
 
+use crate::report;
+
 fn modulo(x: isize, m: usize) -> usize {
     ((x % m as isize + if x < 0 { m as isize } else { 0 }) as usize) % m
 }
@@ -68,95 +69,215 @@ impl<T> IndexMut<isize> for CircularBuffer<T> {
     }
 }
 
-#[derive(Debug, Clone)]
-struct ModRange {
-    start: usize,
-    end: usize,
-    len: usize,
+// A node in an implicit treap, ordered by position in the list rather than by key.  The
+// node for each value is stored at that value's original index.
+#[derive(Clone, Debug)]
+struct Node {
+    priority: u64,
+    size: usize,
+    left: Option<usize>,
+    right: Option<usize>,
+    parent: Option<usize>,
 }
 
-impl ModRange {
-    fn new(start: isize, end: isize, len: usize) -> Self {
-        ModRange {
-            start: modulo(start, len),
-            end: modulo(end, len),
-            len,
-        }
-    }
-    fn contains(&self, value: isize) -> bool {
-        let value = modulo(value, self.len);
-        match self.end.cmp(&self.start) {
-            Ordering::Greater => value >= self.start && value < self.end,
-            Ordering::Less => value >= self.start || value < self.end,
-            Ordering::Equal => false,
+impl Node {
+    fn new(index: usize) -> Self {
+        // The priorities only need to look random to keep the tree balanced.
+        let mut priority = (index as u64).wrapping_add(0x9e3779b97f4a7c15);
+        priority = (priority ^ (priority >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        priority = (priority ^ (priority >> 27)).wrapping_mul(0x94d049bb133111eb);
+        Node {
+            priority: priority ^ (priority >> 31),
+            size: 1,
+            left: None,
+            right: None,
+            parent: None,
         }
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+// Tracks where each value has moved to.  The treap holds the values in order, and the whole
+// list is additionally rotated right by `offset`.
+#[derive(Clone)]
 struct Permutation {
-    indices: Vec<usize>,
+    nodes: Vec<Node>,
+    root: Option<usize>,
+    offset: usize,
+}
+
+impl Debug for Permutation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.indices().fmt(f)
+    }
+}
+
+impl PartialEq for Permutation {
+    fn eq(&self, other: &Self) -> bool {
+        self.indices() == other.indices()
+    }
 }
 
+impl Eq for Permutation {}
+
 impl From<Vec<usize>> for Permutation {
     fn from(indices: Vec<usize>) -> Self {
-        Permutation { indices }
+        indices.into_iter().collect()
     }
 }
 
 impl FromIterator<usize> for Permutation {
     fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
-        Permutation {
-            indices: iter.into_iter().collect(),
+        let indices = iter.into_iter().collect::<Vec<_>>();
+        let mut order = vec![0; indices.len()];
+        for (start_index, end_index) in indices.iter().enumerate() {
+            order[*end_index] = start_index;
         }
+        Permutation::with_order(&order)
     }
 }
 
 impl Permutation {
     fn new(len: usize) -> Self {
-        Permutation {
-            indices: (0..len).collect(),
+        Permutation::with_order(&(0..len).collect::<Vec<_>>())
+    }
+
+    fn with_order(order: &[usize]) -> Self {
+        let mut permutation = Permutation {
+            nodes: (0..order.len()).map(Node::new).collect(),
+            root: None,
+            offset: 0,
+        };
+        for &index in order {
+            permutation.root = permutation.merge(permutation.root, Some(index));
         }
+        permutation
     }
 
-    fn shift(&mut self, start_index: usize, diff: isize) {
-        let len = self.indices.len();
-        let current_index = self.indices[start_index] as isize;
-        let wraps = diff / (len as isize - 1);
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
 
-        let new_index = current_index + diff % (len as isize - 1);
-        let (shift_range, shift_offset) = if new_index < current_index {
-            (ModRange::new(new_index, current_index, len), 1)
-        } else {
-            (ModRange::new(current_index + 1, new_index + 1, len), -1)
+    fn size(&self, node: Option<usize>) -> usize {
+        node.map_or(0, |node| self.nodes[node].size)
+    }
+
+    fn update(&mut self, node: usize) {
+        let Node { left, right, .. } = self.nodes[node];
+        self.nodes[node].size = 1 + self.size(left) + self.size(right);
+        for child in [left, right].into_iter().flatten() {
+            self.nodes[child].parent = Some(node);
+        }
+    }
+
+    // Splits the tree so that the first `count` values are on the left.
+    fn split(&mut self, node: Option<usize>, count: usize) -> (Option<usize>, Option<usize>) {
+        let Some(node) = node else {
+            return (None, None);
         };
+        self.nodes[node].parent = None;
+
+        let left_size = self.size(self.nodes[node].left);
+        if count <= left_size {
+            let (left, right) = self.split(self.nodes[node].left, count);
+            self.nodes[node].left = right;
+            self.update(node);
+            (left, Some(node))
+        } else {
+            let (left, right) = self.split(self.nodes[node].right, count - left_size - 1);
+            self.nodes[node].right = left;
+            self.update(node);
+            (Some(node), right)
+        }
+    }
 
-        for index in self.indices.iter_mut() {
-            if *index as isize == current_index {
-                *index = modulo(current_index + diff, len);
-            } else {
-                let shift = if shift_range.contains(*index as isize) {
-                    shift_offset
+    fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+        match (left, right) {
+            (None, tree) | (tree, None) => tree,
+            (Some(left), Some(right)) => {
+                let root = if self.nodes[left].priority > self.nodes[right].priority {
+                    self.nodes[left].right = self.merge(self.nodes[left].right, Some(right));
+                    left
                 } else {
-                    0
+                    self.nodes[right].left = self.merge(Some(left), self.nodes[right].left);
+                    right
                 };
-                *index = modulo(*index as isize - wraps + shift, len);
+                self.update(root);
+                self.nodes[root].parent = None;
+                Some(root)
+            }
+        }
+    }
+
+    fn position(&self, mut node: usize) -> usize {
+        let mut position = self.size(self.nodes[node].left);
+        while let Some(parent) = self.nodes[node].parent {
+            if self.nodes[parent].right == Some(node) {
+                position += self.size(self.nodes[parent].left) + 1;
+            }
+            node = parent;
+        }
+        position
+    }
+
+    fn shift(&mut self, start_index: usize, diff: isize) {
+        let len = self.len();
+        // With no other values to move past there's nowhere for the value to go.
+        if len <= 1 {
+            return;
+        }
+
+        let tree_index = self.position(start_index);
+        let current_index = (tree_index + self.offset) % len;
+
+        let (before, rest) = self.split(self.root, tree_index);
+        let (_, after) = self.split(rest, 1);
+        let remaining = self.merge(before, after);
+
+        let insert_at = modulo(tree_index as isize + diff, len - 1);
+        let (before, after) = self.split(remaining, insert_at);
+        let with_value = self.merge(before, Some(start_index));
+        self.root = self.merge(with_value, after);
+
+        // The list is circular so the value could equally be placed at the start or end of the
+        // others, pick the rotation that puts it at the expected index.
+        let new_index = modulo(current_index as isize + diff, len);
+        self.offset = (new_index + len - insert_at) % len;
+    }
+
+    fn order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.len());
+        let mut stack = vec![];
+        let mut node = self.root;
+        while node.is_some() || !stack.is_empty() {
+            while let Some(current) = node {
+                stack.push(current);
+                node = self.nodes[current].left;
             }
+            let current = stack.pop().unwrap();
+            order.push(current);
+            node = self.nodes[current].right;
         }
+        order
+    }
+
+    fn indices(&self) -> Vec<usize> {
+        let mut indices = vec![0; self.len()];
+        for (tree_index, start_index) in self.order().into_iter().enumerate() {
+            indices[start_index] = (tree_index + self.offset) % self.len();
+        }
+        indices
     }
 
     #[allow(unused)]
     fn is_valid(&self) -> bool {
-        let mut indices = self.indices.clone();
+        let mut indices = self.indices();
         indices.sort();
         indices == (0..indices.len()).collect::<Vec<_>>()
     }
 
     fn apply<T: Default + Clone>(&self, initial: &CircularBuffer<T>) -> CircularBuffer<T> {
-        let mut end = repeat(T::default())
-            .take(self.indices.len())
-            .collect::<Vec<_>>();
-        for (start_index, end_index) in self.indices.iter().enumerate() {
+        let mut end = repeat(T::default()).take(self.len()).collect::<Vec<_>>();
+        for (start_index, end_index) in self.indices().iter().enumerate() {
             end[*end_index] = initial[start_index as isize].clone()
         }
         end.into()
@@ -175,22 +296,28 @@ fn mix(initial: &CircularBuffer<isize>, num_times: usize) -> CircularBuffer<isiz
     permutation.apply(initial)
 }
 
-fn get_grove_coordinates(
+pub const GROVE_OFFSETS: [isize; 3] = [1000, 2000, 3000];
+
+pub fn get_grove_coordinates(
     start: &CircularBuffer<isize>,
     decryption_key: Option<isize>,
     num_times: usize,
-) -> (isize, isize, isize) {
+    offsets: &[isize],
+) -> Result<Vec<isize>, Error> {
     let values = start
         .iter()
         .map(|val| val * decryption_key.unwrap_or(1))
         .collect();
     let end_values = mix(&values, num_times);
-    let start_pos = end_values.iter().find_position(|x| **x == 0).unwrap().0 as isize;
-    (
-        end_values[start_pos + 1000],
-        end_values[start_pos + 2000],
-        end_values[start_pos + 3000],
-    )
+    let start_pos = end_values
+        .iter()
+        .find_position(|x| **x == 0)
+        .ok_or_else(|| err_msg("No zero in the list"))?
+        .0 as isize;
+    Ok(offsets
+        .iter()
+        .map(|offset| end_values[start_pos + offset])
+        .collect())
 }
 
 pub struct Solver {}
@@ -205,17 +332,131 @@ impl super::Solver for Solver {
     }
 
     fn solve(values: Self::Problem) -> (Option<String>, Option<String>) {
-        let (x, y, z) = get_grove_coordinates(&values, None, 1);
-        let part_one = (x + y + z).to_string();
-        let (x, y, z) = get_grove_coordinates(&values, Some(811589153), 10);
-        let part_two = (x + y + z).to_string();
-        (Some(part_one), Some(part_two))
+        let part_one = get_grove_coordinates(&values, None, 1, &GROVE_OFFSETS)
+            .map(|coordinates| coordinates.iter().sum::<isize>());
+        let part_two = get_grove_coordinates(&values, Some(811589153), 10, &GROVE_OFFSETS)
+            .map(|coordinates| coordinates.iter().sum::<isize>());
+        (report(1, part_one), report(2, part_two))
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::Solver;
+
+    const SAMPLE: &str = "1
+2
+-3
+3
+-2
+0
+4
+";
+
+    #[test]
+    fn test_sample() {
+        let values = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            get_grove_coordinates(&values, None, 1, &GROVE_OFFSETS).unwrap(),
+            vec![4, -3, 2]
+        );
+        assert_eq!(
+            get_grove_coordinates(&values, Some(811589153), 10, &GROVE_OFFSETS).unwrap(),
+            vec![811589153, 2434767459, -1623178306]
+        );
+        assert_eq!(
+            get_grove_coordinates(&values, None, 1, &[0, 1, -1, 7]).unwrap(),
+            vec![0, 3, 4, 0]
+        );
+        assert_eq!(
+            super::Solver::solve(values),
+            (Some("3".to_string()), Some("1623178306".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_matches_list() {
+        let diffs = [5, -7, 0, 12, 3, -1, 25, -30, 6];
+        let len = diffs.len();
+        let mut permutation = Permutation::new(len);
+        let mut list = (0..len).collect::<Vec<_>>();
+
+        for round in 0..3 {
+            for (start_index, diff) in diffs.iter().enumerate() {
+                permutation.shift(start_index, diff * (round + 1));
+                assert!(permutation.is_valid());
+
+                let position = list.iter().position(|&i| i == start_index).unwrap();
+                list.remove(position);
+                let new_position = modulo(position as isize + diff * (round + 1), len - 1);
+                list.insert(new_position, start_index);
+            }
+        }
+
+        // The lists are circular so only compare the order following the first value.
+        let order = permutation
+            .apply(&(0..len).collect::<CircularBuffer<_>>())
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        let rotate = |order: &[usize]| {
+            let start = order.iter().position(|&i| i == 0).unwrap();
+            order[start..]
+                .iter()
+                .chain(order[..start].iter())
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rotate(&order), rotate(&list));
+    }
+
+    #[test]
+    fn test_single_value() {
+        let values = vec![0].into();
+        assert_eq!(
+            get_grove_coordinates(&values, Some(811589153), 10, &GROVE_OFFSETS).unwrap(),
+            vec![0, 0, 0]
+        );
+
+        let mut permutation = Permutation::new(1);
+        permutation.shift(0, 5);
+        assert_eq!(permutation, vec![0].into());
+    }
+
+    #[test]
+    fn test_all_zero() {
+        let values = vec![0; 5].into();
+        assert_eq!(
+            get_grove_coordinates(&values, None, 1, &GROVE_OFFSETS).unwrap(),
+            vec![0, 0, 0]
+        );
+        assert_eq!(mix(&values, 3), values);
+    }
+
+    #[test]
+    fn test_no_zero() {
+        let values = vec![1, 2, 3].into();
+        assert!(get_grove_coordinates(&values, None, 1, &GROVE_OFFSETS).is_err());
+        assert_eq!(super::Solver::solve(values), (None, None));
+    }
+
+    #[test]
+    fn test_shift_multiple_of_others() {
+        // Moving a value past every other value a whole number of times leaves the circular
+        // order unchanged, though the list may have rotated.
+        let values = (0..5).collect::<CircularBuffer<_>>();
+        for diff in [-8, -4, 0, 4, 8, 12] {
+            let mut permutation = Permutation::new(5);
+            permutation.shift(2, diff);
+            let end = permutation.apply(&values);
+            let start = end.iter().position(|&i| i == 0).unwrap() as isize;
+            assert_eq!(
+                (0..5).map(|i| end[start + i]).collect::<Vec<_>>(),
+                vec![0, 1, 2, 3, 4]
+            );
+        }
+    }
 
     #[test]
     fn test_shift_1() {
@@ -3,6 +3,7 @@ use std::{collections::HashMap, fmt::Display};
 use failure::{err_msg, Error};
 
 use self::parse::parse_input;
+use crate::common::{gcd, lcm};
 
 mod parse {
     use super::{Expression, Instruction, Monkey, Operation, Operator};
@@ -67,8 +68,119 @@ mod parse {
     }
 }
 
-type Monkey = String;
-type Instruction = (Monkey, Expression);
+pub type Monkey = String;
+pub type Instruction = (Monkey, Expression);
+
+pub const ROOT: &str = "root";
+pub const HUMAN: &str = "humn";
+
+/// The numbers the monkeys shout while their expressions are being simplified.
+pub trait Number: Copy + Display + PartialEq + From<i64> {
+    fn add(self, other: Self) -> Self;
+    fn sub(self, other: Self) -> Self;
+    fn mul(self, other: Self) -> Self;
+    fn div(self, other: Self) -> Result<Self, Error>;
+    fn to_integer(self) -> Result<i64, Error>;
+}
+
+impl Number for i64 {
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn sub(self, other: Self) -> Self {
+        self - other
+    }
+
+    fn mul(self, other: Self) -> Self {
+        self * other
+    }
+
+    fn div(self, other: Self) -> Result<Self, Error> {
+        if other == 0 || self % other != 0 {
+            Err(err_msg(format!("{} is not divisible by {}", self, other)))
+        } else {
+            Ok(self / other)
+        }
+    }
+
+    fn to_integer(self) -> Result<i64, Error> {
+        Ok(self)
+    }
+}
+
+/// An exact fraction, so that division part way through doesn't need to come out even as long
+/// as the final answer does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rational {
+    num: i64,
+    den: i64,
+}
+
+impl Rational {
+    fn new(num: i64, den: i64) -> Self {
+        let divisor = gcd(num, den) * den.signum();
+        Rational {
+            num: num / divisor,
+            den: den / divisor,
+        }
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(num: i64) -> Self {
+        Rational { num, den: 1 }
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+impl Number for Rational {
+    fn add(self, other: Self) -> Self {
+        let den = lcm(self.den, other.den);
+        Rational::new(
+            self.num * (den / self.den) + other.num * (den / other.den),
+            den,
+        )
+    }
+
+    fn sub(self, other: Self) -> Self {
+        self.add(Rational {
+            num: -other.num,
+            den: other.den,
+        })
+    }
+
+    fn mul(self, other: Self) -> Self {
+        // Cancel across the fractions first to keep the intermediate values small.
+        let a = Rational::new(self.num, other.den);
+        let b = Rational::new(other.num, self.den);
+        Rational::new(a.num * b.num, a.den * b.den)
+    }
+
+    fn div(self, other: Self) -> Result<Self, Error> {
+        if other.num == 0 {
+            return Err(err_msg(format!("{} is not divisible by {}", self, other)));
+        }
+        Ok(self.mul(Rational::new(other.den, other.num)))
+    }
+
+    fn to_integer(self) -> Result<i64, Error> {
+        if self.den == 1 {
+            Ok(self.num)
+        } else {
+            Err(err_msg(format!("{} is not an integer", self)))
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Operator {
@@ -92,13 +204,13 @@ impl Display for Operator {
 }
 
 impl Operator {
-    fn apply(self, left: i64, right: i64) -> i64 {
+    fn apply<N: Number>(self, left: N, right: N) -> Result<N, Error> {
         match self {
-            Operator::Add => left + right,
-            Operator::Sub => left - right,
-            Operator::Multiply => left * right,
-            Operator::Divide => left / right,
-            Operator::Equals => i64::from(left == right),
+            Operator::Add => Ok(left.add(right)),
+            Operator::Sub => Ok(left.sub(right)),
+            Operator::Multiply => Ok(left.mul(right)),
+            Operator::Divide => left.div(right),
+            Operator::Equals => Ok(N::from(i64::from(left == right))),
         }
     }
 
@@ -114,20 +226,20 @@ impl Operator {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Operation {
+pub struct Operation<N = i64> {
     op: Operator,
-    left: Box<Expression>,
-    right: Box<Expression>,
+    left: Box<Expression<N>>,
+    right: Box<Expression<N>>,
 }
 
-impl Display for Operation {
+impl<N: Number> Display for Operation<N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "({} {} {})", self.left, self.op, self.right)
     }
 }
 
-impl Operation {
-    fn expand(&self, expressions: &HashMap<Monkey, Expression>) -> Operation {
+impl<N: Number> Operation<N> {
+    fn expand(&self, expressions: &HashMap<Monkey, Expression>) -> Operation<N> {
         let left = Box::new(self.left.expand(expressions));
         let right = Box::new(self.right.expand(expressions));
         Operation {
@@ -137,25 +249,25 @@ impl Operation {
         }
     }
 
-    fn reduce(&self) -> Expression {
-        let left = self.left.reduce();
-        let right = self.right.reduce();
+    fn reduce(&self) -> Result<Expression<N>, Error> {
+        let left = self.left.reduce()?;
+        let right = self.right.reduce()?;
 
         if let (Some(left), Some(right)) = (left.value(), right.value()) {
-            Expression::Value(self.op.apply(left, right))
+            self.op.apply(left, right).map(Expression::Value)
         } else {
-            Expression::Operation(Operation {
+            Ok(Expression::Operation(Operation {
                 op: self.op,
                 left: Box::new(left),
                 right: Box::new(right),
-            })
+            }))
         }
     }
 
-    fn normalize(&self) -> Expression {
+    fn normalize(&self) -> Result<Expression<N>, Error> {
         let mut op = self.op;
-        let mut left = Box::new(self.left.normalize());
-        let mut right = Box::new(self.right.normalize());
+        let mut left = Box::new(self.left.normalize()?);
+        let mut right = Box::new(self.right.normalize()?);
 
         match self.op {
             Operator::Equals => {
@@ -172,7 +284,7 @@ impl Operation {
                             left: right,
                             right: left_op.right,
                         })
-                        .reduce(),
+                        .reduce()?,
                     );
                 }
             }
@@ -187,10 +299,10 @@ impl Operation {
                     Expression::Operation(Operation {
                         op: Operator::Multiply,
                         left: right,
-                        right: Box::new(Expression::Value(-1)),
+                        right: Box::new(Expression::Value(N::from(-1))),
                     })
-                    .reduce()
-                    .normalize(),
+                    .reduce()?
+                    .normalize()?,
                 );
             }
             Operator::Multiply => {
@@ -205,8 +317,8 @@ impl Operation {
                                     left: left_op.left.clone(),
                                     right: right.clone(),
                                 }
-                                .reduce()
-                                .normalize(),
+                                .reduce()?
+                                .normalize()?,
                             );
                             right = Box::new(
                                 Operation {
@@ -214,8 +326,8 @@ impl Operation {
                                     left: left_op.right.clone(),
                                     right: right.clone(),
                                 }
-                                .reduce()
-                                .normalize(),
+                                .reduce()?
+                                .normalize()?,
                             );
                         }
                         _ => {}
@@ -232,19 +344,19 @@ impl Operation {
         if op != self.op {
             expression.normalize()
         } else {
-            expression
+            Ok(expression)
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Expression {
-    Value(i64),
-    Operation(Operation),
+pub enum Expression<N = i64> {
+    Value(N),
+    Operation(Operation<N>),
     Variable(Monkey),
 }
 
-impl Display for Expression {
+impl<N: Number> Display for Expression<N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Expression::Value(x) => write!(f, "{}", x),
@@ -254,7 +366,8 @@ impl Display for Expression {
     }
 }
 
-impl Expression {
+impl<N: Number> Expression<N> {
+    // Expands the monkeys' expressions in terms of the chosen kind of number.
     fn expand(&self, expressions: &HashMap<Monkey, Expression>) -> Self {
         match self {
             Expression::Value(_) => self.clone(),
@@ -263,7 +376,7 @@ impl Expression {
             }
             Expression::Variable(monkey) => {
                 if let Some(expression) = expressions.get(monkey) {
-                    expression.expand(expressions)
+                    expression.convert::<N>().expand(expressions)
                 } else {
                     self.clone()
                 }
@@ -271,18 +384,18 @@ impl Expression {
         }
     }
 
-    fn reduce(&self) -> Self {
+    fn reduce(&self) -> Result<Self, Error> {
         if let Expression::Operation(operation) = self {
             operation.reduce()
         } else {
-            self.clone()
+            Ok(self.clone())
         }
     }
 
-    fn normalize(&self) -> Self {
+    fn normalize(&self) -> Result<Self, Error> {
         match self {
             Expression::Operation(operation) => operation.normalize(),
-            _ => self.clone(),
+            _ => Ok(self.clone()),
         }
     }
 
@@ -290,7 +403,7 @@ impl Expression {
         matches!(self, Expression::Value(_))
     }
 
-    fn value(&self) -> Option<i64> {
+    fn value(&self) -> Option<N> {
         if let Expression::Value(x) = self {
             Some(*x)
         } else {
@@ -302,13 +415,28 @@ impl Expression {
         matches!(self, Expression::Operation(_))
     }
 
-    fn operation(&self) -> Option<&Operation> {
+    fn operation(&self) -> Option<&Operation<N>> {
         if let Expression::Operation(operation) = self {
             Some(operation)
         } else {
             None
         }
     }
+}
+
+impl Expression {
+    // Converts the values in the expression into another kind of number.
+    fn convert<N: Number>(&self) -> Expression<N> {
+        match self {
+            Expression::Value(x) => Expression::Value(N::from(*x)),
+            Expression::Variable(monkey) => Expression::Variable(monkey.clone()),
+            Expression::Operation(operation) => Expression::Operation(Operation {
+                op: operation.op,
+                left: Box::new(operation.left.convert()),
+                right: Box::new(operation.right.convert()),
+            }),
+        }
+    }
 
     fn operation_mut(&mut self) -> Option<&mut Operation> {
         if let Expression::Operation(operation) = self {
@@ -319,28 +447,45 @@ impl Expression {
     }
 }
 
-fn what_does_the_monkey_shout(instructions: &[Instruction], target: Monkey) -> Result<i64, Error> {
+fn shout<N: Number>(instructions: &[Instruction], target: Monkey) -> Result<i64, Error> {
     let instructions = instructions.iter().cloned().collect::<HashMap<_, _>>();
     let outcome = instructions
         .get(&target)
         .ok_or_else(|| err_msg("Failed to find target"))?
+        .convert::<N>()
         .expand(&instructions)
-        .reduce();
+        .reduce()?;
 
     if let Some(x) = outcome.value() {
-        Ok(x)
+        x.to_integer()
     } else {
         Err(err_msg(format!("{} is not fully reduced", outcome)))
     }
 }
 
-fn what_should_i_shout(
+pub fn what_does_the_monkey_shout(
     instructions: &[Instruction],
     target: Monkey,
-    me: Monkey,
 ) -> Result<i64, Error> {
+    shout::<i64>(instructions, target)
+}
+
+/// Like `what_does_the_monkey_shout`, but only requires the final answer to be a whole number
+/// rather than every division along the way.
+pub fn what_does_the_monkey_shout_exactly(
+    instructions: &[Instruction],
+    target: Monkey,
+) -> Result<i64, Error> {
+    shout::<Rational>(instructions, target)
+}
+
+fn normalized_equation<N: Number>(
+    instructions: &[Instruction],
+    target: Monkey,
+    me: &Monkey,
+) -> Result<Operation<N>, Error> {
     let mut instructions = instructions.iter().cloned().collect::<HashMap<_, _>>();
-    instructions.remove(&me);
+    instructions.remove(me);
     instructions
         .get_mut(&target)
         .ok_or_else(|| err_msg("Failed to find target"))?
@@ -351,9 +496,10 @@ fn what_should_i_shout(
     let reduced = instructions
         .get(&target)
         .ok_or_else(|| err_msg("Failed to find target"))?
+        .convert::<N>()
         .expand(&instructions)
-        .reduce();
-    let normalized = reduced.normalize();
+        .reduce()?;
+    let normalized = reduced.normalize()?;
 
     let operation = normalized
         .operation()
@@ -363,6 +509,28 @@ fn what_should_i_shout(
         return Err(err_msg(format!("Not an equality: {}", operation)));
     }
 
+    Ok(operation.clone())
+}
+
+pub fn equation_for(
+    instructions: &[Instruction],
+    target: Monkey,
+    me: Monkey,
+) -> Result<String, Error> {
+    let equation = normalized_equation::<i64>(instructions, target, &me)?;
+    Ok(format!(
+        "{} {} {}",
+        equation.left, equation.op, equation.right
+    ))
+}
+
+fn solve_for<N: Number>(
+    instructions: &[Instruction],
+    target: Monkey,
+    me: Monkey,
+) -> Result<i64, Error> {
+    let operation = normalized_equation::<N>(instructions, target, &me)?;
+
     if *operation.left != Expression::Variable(me) {
         return Err(err_msg(format!(
             "Failed to normalize expression: {}",
@@ -373,7 +541,26 @@ fn what_should_i_shout(
     operation
         .right
         .value()
-        .ok_or_else(|| err_msg(format!("Failed to normalize expression: {}", operation)))
+        .ok_or_else(|| err_msg(format!("Failed to normalize expression: {}", operation)))?
+        .to_integer()
+}
+
+pub fn what_should_i_shout(
+    instructions: &[Instruction],
+    target: Monkey,
+    me: Monkey,
+) -> Result<i64, Error> {
+    solve_for::<i64>(instructions, target, me)
+}
+
+/// Like `what_should_i_shout`, but only requires the answer to be a whole number rather than
+/// every division along the way.
+pub fn what_should_i_shout_exactly(
+    instructions: &[Instruction],
+    target: Monkey,
+    me: Monkey,
+) -> Result<i64, Error> {
+    solve_for::<Rational>(instructions, target, me)
 }
 
 pub struct Solver {}
@@ -386,12 +573,179 @@ impl super::Solver for Solver {
     }
 
     fn solve(instructions: Self::Problem) -> (Option<String>, Option<String>) {
-        let part_one = what_does_the_monkey_shout(&instructions, "root".to_string())
+        let part_one = what_does_the_monkey_shout(&instructions, ROOT.to_string())
             .expect("Failed to solve part one")
             .to_string();
-        let part_two = what_should_i_shout(&instructions, "root".to_string(), "humn".to_string())
+        let part_two = what_should_i_shout(&instructions, ROOT.to_string(), HUMAN.to_string())
             .expect("Failed to solve part two")
             .to_string();
         (Some(part_one), Some(part_two))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        equation_for, what_does_the_monkey_shout, what_does_the_monkey_shout_exactly,
+        what_should_i_shout, what_should_i_shout_exactly, Number, Rational,
+    };
+    use crate::Solver;
+
+    const SAMPLE: &str = "root: pppw + sjmn
+dbpl: 5
+cczh: sllz + lgvd
+zczc: 2
+ptdq: humn - dvpt
+dvpt: 3
+lfqf: 4
+humn: 5
+ljgn: 2
+sjmn: drzm * dbpl
+sllz: 4
+pppw: cczh / lfqf
+lgvd: ljgn * ptdq
+drzm: hmdt - zczc
+hmdt: 32
+";
+
+    #[test]
+    fn test_sample() {
+        let instructions = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            what_does_the_monkey_shout(&instructions, "root".to_string()).unwrap(),
+            152
+        );
+        assert_eq!(
+            what_should_i_shout(&instructions, "root".to_string(), "humn".to_string()).unwrap(),
+            301
+        );
+    }
+
+    #[test]
+    fn test_equation_for() {
+        let instructions = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            equation_for(&instructions, "root".to_string(), "humn".to_string()).unwrap(),
+            "humn = 301"
+        );
+
+        // If the unknown can't be isolated the partially normalized equation is still shown.
+        let data = "root: abcd + efgh
+abcd: humn * humn
+efgh: 16
+humn: 5
+";
+        let instructions = super::Solver::parse_input(data.to_string()).unwrap();
+        assert_eq!(
+            equation_for(&instructions, "root".to_string(), "humn".to_string()).unwrap(),
+            "(humn * humn) = 16"
+        );
+        assert!(
+            what_should_i_shout(&instructions, "root".to_string(), "humn".to_string()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_renamed_monkeys() {
+        let data = SAMPLE
+            .replace("root", "top")
+            .replace("humn", "self")
+            .replace("sjmn", "humn");
+        let instructions = super::Solver::parse_input(data).unwrap();
+        assert_eq!(
+            what_does_the_monkey_shout(&instructions, "top".to_string()).unwrap(),
+            152
+        );
+        assert_eq!(
+            what_should_i_shout(&instructions, "top".to_string(), "self".to_string()).unwrap(),
+            301
+        );
+        assert!(
+            what_should_i_shout(&instructions, "root".to_string(), "self".to_string()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_uneven_division() {
+        let data = "root: abcd / efgh
+abcd: 7
+efgh: 2
+";
+        let instructions = super::Solver::parse_input(data.to_string()).unwrap();
+        assert!(what_does_the_monkey_shout(&instructions, "root".to_string()).is_err());
+
+        let data = "root: abcd + efgh
+abcd: humn / efgh
+efgh: 2
+humn: 5
+";
+        let instructions = super::Solver::parse_input(data.to_string()).unwrap();
+        assert!(what_does_the_monkey_shout(&instructions, "root".to_string()).is_err());
+
+        // Solving for humn would need it to be 3 / 2.
+        let data = "root: abcd + efgh
+abcd: humn * cdef
+cdef: 2
+efgh: 3
+humn: 5
+";
+        let instructions = super::Solver::parse_input(data.to_string()).unwrap();
+        assert!(
+            what_should_i_shout(&instructions, "root".to_string(), "humn".to_string()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_exact_division() {
+        // humn / four doesn't come out even, but multiplying by six afterwards does.
+        let data = "root: abcd + efgh
+abcd: wxyz * cdef
+wxyz: humn / four
+four: 4
+cdef: 6
+efgh: 9
+humn: 6
+";
+        let instructions = super::Solver::parse_input(data.to_string()).unwrap();
+        let root = || "root".to_string();
+        let humn = || "humn".to_string();
+        assert!(what_does_the_monkey_shout(&instructions, root()).is_err());
+        assert_eq!(
+            what_does_the_monkey_shout_exactly(&instructions, root()).unwrap(),
+            18
+        );
+        assert!(what_should_i_shout(&instructions, root(), humn()).is_err());
+        assert_eq!(
+            what_should_i_shout_exactly(&instructions, root(), humn()).unwrap(),
+            6
+        );
+
+        // The answer itself still has to be a whole number.
+        let instructions = super::Solver::parse_input(data.replace("humn: 6", "humn: 5")).unwrap();
+        assert!(what_does_the_monkey_shout_exactly(&instructions, root()).is_err());
+        let instructions = super::Solver::parse_input(data.replace("efgh: 9", "efgh: 8")).unwrap();
+        assert!(what_should_i_shout_exactly(&instructions, root(), humn()).is_err());
+
+        let instructions = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            what_does_the_monkey_shout_exactly(&instructions, root()).unwrap(),
+            152
+        );
+        assert_eq!(
+            what_should_i_shout_exactly(&instructions, root(), humn()).unwrap(),
+            301
+        );
+    }
+
+    #[test]
+    fn test_rational() {
+        let half = Rational::from(1).div(Rational::from(2)).unwrap();
+        let third = Rational::from(-1).div(Rational::from(-3)).unwrap();
+        assert_eq!(half.add(third).to_string(), "5/6");
+        assert_eq!(half.sub(third).to_string(), "1/6");
+        assert_eq!(third.sub(half).to_string(), "-1/6");
+        assert_eq!(half.mul(Rational::from(4)).to_integer().unwrap(), 2);
+        assert!(half.to_integer().is_err());
+        assert!(half.div(Rational::from(0)).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     common::{int_sqrt, Direction, Position, Rotation},
     parsers::signed,
+    report,
 };
 use failure::{err_msg, Error};
 use nom::{
@@ -14,7 +15,7 @@ use std::{
     array,
     collections::{HashMap, HashSet},
     fmt::Debug,
-    io::{stdout, Write},
+    io::{self, Write},
     ops::RangeInclusive,
 };
 
@@ -32,8 +33,7 @@ fn parse_directions(input: &str) -> Result<Box<[Movement]>, Error> {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-
-struct FlatLocation {
+pub struct FlatLocation {
     position: Position,
     direction: Direction,
 }
@@ -94,7 +94,7 @@ impl<'a, T: IntoIterator<Item = &'a str>> From<T> for FlatMap {
             .collect::<Vec<_>>()
             .into_boxed_slice();
 
-        let col_extents = (1..=max_x)
+        let col_extents = (0..=max_x)
             .map(|x| {
                 let min = (0..=max_y)
                     .find(|&y| occupied.contains_key(&Position { x, y }))
@@ -176,13 +176,28 @@ pub enum Movement {
 
 impl Movement {
     fn apply<M: Map>(self, map: &M, location: &mut M::Location) {
+        self.apply_with(map, location, |_| ())
+    }
+
+    // Applies the movement, calling `visit` with the location after each turn
+    // and each step that is actually taken.
+    fn apply_with<M: Map, F: FnMut(M::Location)>(
+        self,
+        map: &M,
+        location: &mut M::Location,
+        mut visit: F,
+    ) {
         match self {
-            Movement::Turn(rotation) => location.turn(rotation),
+            Movement::Turn(rotation) => {
+                location.turn(rotation);
+                visit(*location);
+            }
             Movement::Move(distance) => {
                 for _ in 0..distance {
                     let new_location = map.next_step(*location);
                     if !map.occupied(new_location) {
-                        *location = new_location
+                        *location = new_location;
+                        visit(*location);
                     } else {
                         break;
                     }
@@ -213,8 +228,23 @@ fn find_end_location<M: Map>(map: &M, directions: &[Movement]) -> FlatLocation {
     map.flatten(location)
 }
 
+/// Returns every location visited while following the directions, starting with the start
+/// location and with one entry for each turn and each step taken.
+pub fn trace<M: Map>(map: &M, directions: &[Movement]) -> Vec<FlatLocation> {
+    let mut location = map.start_location();
+    let mut trace = vec![map.flatten(location)];
+
+    for movement in directions {
+        movement.apply_with(map, &mut location, |location| {
+            trace.push(map.flatten(location))
+        });
+    }
+
+    trace
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct CubeLocation {
+pub struct CubeLocation {
     side: u8,
     location: FlatLocation,
 }
@@ -225,7 +255,7 @@ impl Location for CubeLocation {
     }
 }
 
-struct CubeMap {
+pub struct CubeMap {
     map: FlatMap,
     side_length: u64,
 
@@ -285,7 +315,10 @@ impl CubeMap {
         }
     }
 
-    fn find_sides(map: &FlatMap, side_length: u64) -> [(Position, Direction); 6] {
+    // Walks the net in the flat map from the first side, using the adjacencies
+    // of the canonical layout to work out which side each square of the net
+    // folds onto and which way up it is.  This works for any unfolding.
+    fn find_sides(map: &FlatMap, side_length: u64) -> Result<[(Position, Direction); 6], Error> {
         let side_0_pos = Position {
             x: *map.extent_for_row(Position::ORIGIN).start(),
             y: 0,
@@ -300,9 +333,25 @@ impl CubeMap {
         added.insert(side_0_pos);
 
         while let Some((side, position, up)) = stack.pop() {
-            found_positions.insert(side, (position, up));
+            if found_positions.insert(side, (position, up)).is_some() {
+                return Err(err_msg(format!(
+                    "Map doesn't fold into a cube, side {} appears twice",
+                    side
+                )));
+            }
             added.insert(position);
 
+            let length = side_length as i64;
+            if (0..length)
+                .flat_map(|y| (0..length).map(move |x| position + Position { x, y }))
+                .any(|cell| !map.occupied.contains_key(&cell))
+            {
+                return Err(err_msg(format!(
+                    "Side {} at {:?} isn't completely filled",
+                    side, position
+                )));
+            }
+
             for direction in Direction::all() {
                 let next_pos = position + direction.delta() * side_length as i64;
                 if map.occupied.contains_key(&next_pos) && !added.contains(&next_pos) {
@@ -317,11 +366,16 @@ impl CubeMap {
             }
         }
 
-        array::from_fn(|side| {
-            *found_positions
-                .get(&(side as u8))
-                .unwrap_or_else(|| panic!("Failed to find side {}", side))
-        })
+        if found_positions.len() < 6 {
+            return Err(err_msg(format!(
+                "Map doesn't fold into a cube, only {} of the 6 sides are connected",
+                found_positions.len()
+            )));
+        }
+
+        // Every cell has been accounted for, as there are six completely filled sides and
+        // exactly enough cells to fill them.
+        Ok(array::from_fn(|side| found_positions[&(side as u8)]))
     }
 }
 
@@ -405,7 +459,7 @@ impl Map for CubeMap {
         }
     }
 
-    fn draw<W: Write>(&self, mut writer: W, location: Option<Self::Location>) {
+    fn draw<W: Write>(&self, mut writer: W, location: Option<Self::Location>) -> io::Result<()> {
         let side_positions = self
             .sides
             .iter()
@@ -468,48 +522,62 @@ impl Map for CubeMap {
 
         if let Some(loc) = location {
             let loc = self.flatten(loc);
-            let mut x = loc.position.x / 10;
+            let mut x = loc.position.x * 5 / self.side_length as i64;
             x += x / 5 + 1;
-            let mut y = loc.position.y / 10;
+            let mut y = loc.position.y * 5 / self.side_length as i64;
             y += y / 5 + 1;
 
             display.insert((x as u64, y as u64), loc.direction.as_char());
         }
 
         for y in 0..grid_height * 6 + 1 {
-            for x in 0..grid_height * 6 + 1 {
-                write!(writer, "{}", display.get(&(x, y)).unwrap_or(&' ')).unwrap();
+            for x in 0..grid_width * 6 + 1 {
+                write!(writer, "{}", display.get(&(x, y)).unwrap_or(&' '))?;
             }
-            writeln!(writer).unwrap();
+            writeln!(writer)?;
         }
+
+        Ok(())
     }
 }
 
-impl From<FlatMap> for CubeMap {
-    fn from(map: FlatMap) -> Self {
-        let side_length = int_sqrt(map.occupied.len() as u64 / 6).expect("Not a cube");
-        let sides = Self::find_sides(&map, side_length);
-
-        CubeMap {
+impl TryFrom<FlatMap> for CubeMap {
+    type Error = Error;
+
+    fn try_from(map: FlatMap) -> Result<Self, Self::Error> {
+        let num_cells = map.occupied.len() as u64;
+        let side_length = int_sqrt(num_cells / 6)
+            .filter(|side_length| *side_length > 0 && num_cells % 6 == 0)
+            .ok_or_else(|| {
+                err_msg(format!(
+                    "Map has {} cells, which can't cover the six sides of a cube",
+                    num_cells
+                ))
+            })?;
+        let sides = Self::find_sides(&map, side_length)?;
+
+        Ok(CubeMap {
             map,
             side_length,
             sides,
-        }
+        })
     }
 }
 
-trait Location: Clone + Copy {
+pub trait Location: Clone + Copy {
     fn turn(&mut self, rotation: Rotation);
 }
 
-trait Map {
+pub trait Map {
     type Location: Location + Debug;
 
     fn start_location(&self) -> Self::Location;
     fn next_step(&self, loc: Self::Location) -> Self::Location;
     fn occupied(&self, loc: Self::Location) -> bool;
     fn flatten(&self, location: Self::Location) -> FlatLocation;
-    fn draw<W: Write>(&self, _: W, _: Option<Self::Location>) {}
+    fn draw<W: Write>(&self, _: W, _: Option<Self::Location>) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 pub struct Solver {}
@@ -528,10 +596,361 @@ impl super::Solver for Solver {
     fn solve((map, directions): Self::Problem) -> (Option<String>, Option<String>) {
         let part_one = score(find_end_location(&map, &directions)).to_string();
 
-        let cube_map = CubeMap::from(map);
-        cube_map.draw(stdout(), None);
+        let part_two =
+            CubeMap::try_from(map).map(|cube_map| score(find_end_location(&cube_map, &directions)));
+        (Some(part_one), report(2, part_two))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Solver;
+
+    const SAMPLE: &str = "        ...#
+        .#..
+        #...
+        ....
+...#.......#
+........#...
+..#....#....
+..........#.
+        ...#....
+        .....#..
+        .#......
+        ......#.
+
+10R5L5R10L4R5L5
+";
+
+    fn steps_taken(trace: &[FlatLocation]) -> usize {
+        trace
+            .windows(2)
+            .filter(|pair| pair[0].position != pair[1].position)
+            .count()
+    }
+
+    #[test]
+    fn test_trace() {
+        let (map, directions) = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let num_turns = directions
+            .iter()
+            .filter(|movement| matches!(movement, Movement::Turn(_)))
+            .count();
+        let distance: i64 = directions
+            .iter()
+            .map(|movement| match movement {
+                Movement::Move(distance) => *distance,
+                Movement::Turn(_) => 0,
+            })
+            .sum();
+
+        let flat_trace = trace(&map, &directions);
+        assert_eq!(flat_trace.first(), Some(&map.flatten(map.start_location())));
+        assert_eq!(
+            flat_trace.last(),
+            Some(&find_end_location(&map, &directions))
+        );
+        assert_eq!(flat_trace.len(), 1 + num_turns + steps_taken(&flat_trace));
+        assert!((steps_taken(&flat_trace) as i64) < distance);
+
+        let cube_map = CubeMap::try_from(map).unwrap();
+        let cube_trace = trace(&cube_map, &directions);
+        assert_eq!(
+            cube_trace.last(),
+            Some(&find_end_location(&cube_map, &directions))
+        );
+        assert_eq!(cube_trace.len(), 1 + num_turns + steps_taken(&cube_trace));
+
+        // Without any walls every step of every move is taken.
+        let open = SAMPLE.replace('#', ".");
+        let (map, directions) = super::Solver::parse_input(open).unwrap();
+        assert_eq!(steps_taken(&trace(&map, &directions)) as i64, distance);
+        let cube_map = CubeMap::try_from(map).unwrap();
+        assert_eq!(steps_taken(&trace(&cube_map, &directions)) as i64, distance);
+    }
+
+    #[test]
+    fn test_not_a_cube() {
+        let try_cube = |data: &str| CubeMap::try_from(FlatMap::from(data.lines()));
+
+        // Too few cells.
+        let err = try_cube("..\n...").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Map has 5 cells, which can't cover the six sides of a cube"
+        );
+
+        // Enough cells, but laid out in a net that doesn't fold.
+        assert!(try_cube("..\n..\n..").is_err());
+        assert!(try_cube("......").is_err());
+
+        // A side that isn't completely filled.
+        let err = try_cube("  ..\n  ..\n......\n.......\n  ..\n  ..\n  ..\n  .")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Side 3 at Position { x: 2, y: 6 } isn't completely filled"
+        );
+
+        // Six sides, but not all connected.
+        let err = try_cube(".\n .\n.\n .\n.\n .").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Map doesn't fold into a cube, only 1 of the 6 sides are connected"
+        );
+
+        let data = "..
+..
+
+1R1
+";
+        let (map, directions) = super::Solver::parse_input(data.to_string()).unwrap();
+        assert_eq!(
+            score(find_end_location(&map, &directions)),
+            2000 + 4 * 2 + 1
+        );
+        assert_eq!(
+            super::Solver::solve((map, directions)),
+            (Some("2009".to_string()), None)
+        );
+    }
+
+    fn cube_map(data: &str) -> CubeMap {
+        CubeMap::try_from(FlatMap::from(data.lines())).unwrap()
+    }
 
-        let part_two = score(find_end_location(&cube_map, &directions)).to_string();
-        (Some(part_one), Some(part_two))
+    // Finds the location on the cube that corresponds to a location on the flat map.
+    fn locate(cube_map: &CubeMap, location: FlatLocation) -> CubeLocation {
+        (0..6)
+            .flat_map(|side| {
+                Direction::all().map(move |direction| CubeLocation {
+                    side,
+                    location: FlatLocation {
+                        position: location.position,
+                        direction,
+                    },
+                })
+            })
+            .flat_map(|cube_location| {
+                let side_length = cube_map.side_length as i64;
+                (0..side_length).flat_map(move |x| {
+                    (0..side_length).map(move |y| CubeLocation {
+                        location: FlatLocation {
+                            position: Position { x, y },
+                            ..cube_location.location
+                        },
+                        ..cube_location
+                    })
+                })
+            })
+            .find(|cube_location| cube_map.flatten(*cube_location) == location)
+            .unwrap()
+    }
+
+    fn step(cube_map: &CubeMap, position: (i64, i64), direction: Direction) -> FlatLocation {
+        let location = locate(
+            cube_map,
+            FlatLocation {
+                position: position.into(),
+                direction,
+            },
+        );
+        cube_map.flatten(cube_map.next_step(location))
+    }
+
+    fn flat(position: (i64, i64), direction: Direction) -> FlatLocation {
+        FlatLocation {
+            position: position.into(),
+            direction,
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let (map, directions) = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(score(find_end_location(&map, &directions)), 6032);
+
+        let cube_map = CubeMap::try_from(map).unwrap();
+        assert_eq!(score(find_end_location(&cube_map, &directions)), 5031);
+
+        let problem = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            super::Solver::solve(problem),
+            (Some("6032".to_string()), Some("5031".to_string()))
+        );
+
+        assert_eq!(
+            step(&cube_map, (11, 5), Direction::East),
+            flat((14, 8), Direction::South)
+        );
+        assert_eq!(
+            step(&cube_map, (10, 11), Direction::South),
+            flat((1, 7), Direction::North)
+        );
+        assert_eq!(
+            step(&cube_map, (6, 4), Direction::North),
+            flat((8, 2), Direction::East)
+        );
+    }
+
+    #[test]
+    fn test_draw() {
+        let (map, _) = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let cube_map = CubeMap::try_from(map).unwrap();
+        let mut output = vec![];
+        cube_map
+            .draw(&mut output, Some(cube_map.start_location()))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "+-----+-----+-----+-----+
+|     |     |>    |     |
+|     |     |     |     |
+|     |     | 0 ^ |     |
+|     |     |     |     |
+|     |     |     |     |
++-----+-----+-----+-----+
+|     |     |     |     |
+|     |     |     |     |
+| 3 v | 4 ^ | 1 ^ |     |
+|     |     |     |     |
+|     |     |     |     |
++-----+-----+-----+-----+
+|     |     |     |     |
+|     |     |     |     |
+|     |     | 2 ^ | 5 > |
+|     |     |     |     |
+|     |     |     |     |
++-----+-----+-----+-----+
+"
+        );
+    }
+
+    #[test]
+    fn test_canonical_layout() {
+        let cube_map = cube_map(
+            "   ...
+   ...
+   ...
+.........
+.........
+.........
+   ...
+   ...
+   ...
+   ...
+   ...
+   ...
+",
+        );
+
+        assert_eq!(
+            step(&cube_map, (4, 0), Direction::North),
+            flat((4, 11), Direction::North)
+        );
+        assert_eq!(
+            step(&cube_map, (0, 4), Direction::West),
+            flat((3, 10), Direction::East)
+        );
+        assert_eq!(
+            step(&cube_map, (8, 5), Direction::East),
+            flat((5, 9), Direction::West)
+        );
+        assert_eq!(
+            step(&cube_map, (5, 11), Direction::East),
+            flat((8, 3), Direction::West)
+        );
+    }
+
+    const NETS: [&str; 11] = [
+        "x...\nxxxx\nx...",
+        "x...\nxxxx\n.x..",
+        "x...\nxxxx\n..x.",
+        "x...\nxxxx\n...x",
+        ".x..\nxxxx\n.x..",
+        ".x..\nxxxx\n..x.",
+        "xx..\n.xxx\n.x..",
+        "xx..\n.xxx\n..x.",
+        "xx..\n.xxx\n...x",
+        "xx..\n.xx.\n..xx",
+        "xxx..\n..xxx",
+    ];
+
+    // Draws a net as a map, after rotating it a number of quarter turns and optionally
+    // mirroring it.
+    fn draw_net(net: &str, quarter_turns: usize, mirror: bool, side_length: usize) -> String {
+        let mut faces = net
+            .lines()
+            .map(|line| line.chars().map(|c| c == 'x').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        if mirror {
+            faces.iter_mut().for_each(|row| row.reverse());
+        }
+        for _ in 0..quarter_turns {
+            faces = (0..faces[0].len())
+                .map(|x| (0..faces.len()).rev().map(|y| faces[y][x]).collect())
+                .collect();
+        }
+
+        faces
+            .iter()
+            .flat_map(|row| {
+                let line = row
+                    .iter()
+                    .map(|&face| if face { "." } else { " " }.repeat(side_length))
+                    .collect::<String>();
+                vec![line.trim_end().to_string(); side_length]
+            })
+            .map(|line| line + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn test_all_nets() {
+        let side_length = 3;
+
+        for net in NETS {
+            for quarter_turns in 0..4 {
+                for mirror in [false, true] {
+                    let data = draw_net(net, quarter_turns, mirror, side_length);
+                    let cube_map = cube_map(&data);
+
+                    for (position, _) in cube_map.map.occupied.iter() {
+                        for direction in Direction::all() {
+                            let start = locate(
+                                &cube_map,
+                                FlatLocation {
+                                    position: *position,
+                                    direction,
+                                },
+                            );
+
+                            // Going straight all the way round the cube gets back to the start.
+                            let mut location = start;
+                            for _ in 0..4 * side_length {
+                                location = cube_map.next_step(location);
+                            }
+                            assert_eq!(
+                                cube_map.flatten(location),
+                                cube_map.flatten(start),
+                                "Failed to go round the cube from {:?} in\n{}",
+                                cube_map.flatten(start),
+                                data
+                            );
+
+                            // Turning round after a step gets back to the start.
+                            let mut location = cube_map.next_step(start);
+                            location.turn(Rotation::HALF);
+                            location = cube_map.next_step(location);
+                            location.turn(Rotation::HALF);
+                            assert_eq!(cube_map.flatten(location), cube_map.flatten(start));
+                        }
+                    }
+                }
+            }
+        }
     }
 }
@@ -1,7 +1,12 @@
 use failure::Error;
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, stdout, Write},
+};
 
 use crate::common::{Bounds, Direction, Position};
+use crate::parsers::positioned_chars;
+use crate::render::render_grid;
 
 const DIRECTIONS: [Direction; 4] = [
     Direction::North,
@@ -66,10 +71,23 @@ fn execute_rounds(elves: &HashSet<Position>, num_rounds: usize) -> HashSet<Posit
     elves
 }
 
+pub const NUM_ROUNDS: usize = 10;
+
+/// Counts the empty ground tiles in the rectangle containing every elf after the given number
+/// of rounds.
+pub fn empty_space_after(elves: &HashSet<Position>, rounds: usize) -> usize {
+    let (width, height) = bounding_box(&execute_rounds(elves, rounds));
+    (width * height) as usize - elves.len()
+}
+
+/// The width and height of the smallest rectangle containing every elf.
+pub fn bounding_box(elves: &HashSet<Position>) -> (i64, i64) {
+    let bounds: Bounds = elves.iter().cloned().into();
+    (bounds.width(), bounds.height())
+}
+
 fn find_empty_space(elves: &HashSet<Position>) -> usize {
-    let end_state = execute_rounds(elves, 10);
-    let bounds: Bounds = end_state.iter().cloned().into();
-    (bounds.width() * bounds.height()) as usize - elves.len()
+    empty_space_after(elves, NUM_ROUNDS)
 }
 
 fn find_rounds_to_stop(elves: &HashSet<Position>) -> usize {
@@ -85,26 +103,24 @@ fn find_rounds_to_stop(elves: &HashSet<Position>) -> usize {
     unreachable!()
 }
 
+pub fn render<W: Write>(elves: &HashSet<Position>, mut writer: W) -> io::Result<()> {
+    let bounds = Bounds::from(elves.iter().cloned());
+    let grid = render_grid(
+        &bounds,
+        |position| {
+            if elves.contains(&position) {
+                '#'
+            } else {
+                '.'
+            }
+        },
+    );
+    write!(writer, "{}", grid)
+}
+
 #[allow(unused)]
 fn display(elves: &HashSet<Position>) {
-    let bounds = Bounds::from(elves.iter().cloned())
-        .non_empty()
-        .cloned()
-        .unwrap();
-
-    for y in bounds.iter_y() {
-        println!(
-            "{}",
-            bounds
-                .iter_x()
-                .map(|x| if elves.contains(&(x, y).into()) {
-                    '#'
-                } else {
-                    '.'
-                })
-                .collect::<String>()
-        );
-    }
+    render(elves, stdout()).unwrap()
 }
 
 pub struct Solver {}
@@ -113,18 +129,8 @@ impl super::Solver for Solver {
     type Problem = HashSet<Position>;
 
     fn parse_input(data: String) -> Result<Self::Problem, Error> {
-        Ok(data
-            .lines()
-            .enumerate()
-            .flat_map(|(y, line)| {
-                line.chars().enumerate().filter_map(move |(x, c)| {
-                    if c == '#' {
-                        Some((x as i64, y as i64).into())
-                    } else {
-                        None
-                    }
-                })
-            })
+        Ok(positioned_chars(&data, 0, 0)
+            .filter_map(|(position, c)| if c == '#' { Some(position) } else { None })
             .collect())
     }
 
@@ -134,3 +140,72 @@ impl super::Solver for Solver {
         (Some(part_one), Some(part_two))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{bounding_box, empty_space_after, execute_rounds, find_rounds_to_stop, render};
+    use crate::Solver;
+
+    const SAMPLE: &str = "....#..
+..###.#
+#...#.#
+.#...##
+#.###..
+##.#.##
+.#..#..
+";
+
+    #[test]
+    fn test_empty_space_after() {
+        let elves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(empty_space_after(&elves, 0), 7 * 7 - 22);
+        assert_eq!(empty_space_after(&elves, 10), 110);
+        assert_eq!(find_rounds_to_stop(&elves), 20);
+        assert_eq!(
+            super::Solver::solve(elves),
+            (Some("110".to_string()), Some("20".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let elves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(bounding_box(&elves), (7, 7));
+
+        let settled = execute_rounds(&elves, 10);
+        assert_eq!(bounding_box(&settled), (12, 11));
+        assert_eq!(12 * 11 - settled.len(), 110);
+
+        assert_eq!(bounding_box(&Default::default()), (0, 0));
+    }
+
+    #[test]
+    fn test_render() {
+        let elves = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        let mut output = vec![];
+        render(&elves, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), SAMPLE);
+
+        let mut output = vec![];
+        render(&execute_rounds(&elves, 10), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "......#.....
+..........#.
+.#.#..#.....
+.....#......
+..#.....#..#
+#......##...
+....##......
+.#........#.
+...#.#..#...
+............
+...#..#..#..
+"
+        );
+
+        let mut output = vec![];
+        render(&Default::default(), &mut output).unwrap();
+        assert!(output.is_empty());
+    }
+}
@@ -1,11 +1,12 @@
 use std::{array, hash::Hash, str::FromStr};
 
-use failure::Error;
-use itertools::chain;
+use failure::{err_msg, Error};
+use itertools::{chain, Itertools};
 
 use crate::{
     a_star,
-    common::{Direction, Position},
+    common::{lcm, Direction, Position},
+    report,
 };
 
 #[derive(Debug)]
@@ -15,6 +16,9 @@ pub struct Map {
     width: i64,
     start: Position,
     end: Position,
+    // Whether each cell of the valley holds a blizzard, for each time up until the blizzards
+    // return to their starting positions.
+    occupied: Box<[Box<[bool]>]>,
 }
 
 impl Map {
@@ -40,23 +44,67 @@ impl Map {
             .map(move |pos| (pos + time as i64 * offset) % modulo)
     }
 
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    pub fn end(&self) -> Position {
+        self.end
+    }
+
+    fn is_in_valley(&self, position: Position) -> bool {
+        position.x >= 0 && position.y >= 0 && position.x < self.width && position.y < self.height
+    }
+
+    fn is_open(&self, position: Position) -> bool {
+        position == self.start || position == self.end || self.is_in_valley(position)
+    }
+
+    fn blizzard_positions_at_time(&self, time: u64) -> impl Iterator<Item = Position> + '_ {
+        Direction::all().flat_map(move |direction| {
+            let num_rows_or_cols = self.blizzards[direction as usize].len() as i64;
+            (0..num_rows_or_cols).flat_map(move |row_or_col| {
+                self.blizzards_in_direction_at_time(direction, row_or_col, time)
+                    .map(move |pos| match direction {
+                        Direction::North | Direction::South => Position {
+                            x: row_or_col,
+                            y: pos,
+                        },
+                        Direction::East | Direction::West => Position {
+                            x: pos,
+                            y: row_or_col,
+                        },
+                    })
+            })
+        })
+    }
+
+    fn find_occupied(&self) -> Box<[Box<[bool]>]> {
+        let period = lcm(self.width, self.height) as u64;
+        (0..period)
+            .map(|time| {
+                let mut occupied = vec![false; (self.width * self.height) as usize];
+                for position in self.blizzard_positions_at_time(time) {
+                    occupied[self.cell_index(position)] = true;
+                }
+                occupied.into_boxed_slice()
+            })
+            .collect()
+    }
+
+    fn cell_index(&self, position: Position) -> usize {
+        (position.y * self.width + position.x) as usize
+    }
+
     fn is_free_at_time(&self, position: Position, time: u64) -> bool {
         if position == self.start || position == self.end {
             return true;
         }
-        if position.x < 0 || position.y < 0 || position.x >= self.width || position.y >= self.height
-        {
+        if !self.is_in_valley(position) {
             return false;
         }
-        Direction::all().all(|direction| {
-            let (row_or_col, check) = match direction {
-                Direction::North | Direction::South => (position.x, position.y),
-                Direction::East | Direction::West => (position.y, position.x),
-            };
-
-            self.blizzards_in_direction_at_time(direction, row_or_col, time)
-                .all(|pos| pos != check)
-        })
+        let occupied = &self.occupied[(time % self.occupied.len() as u64) as usize];
+        !occupied[self.cell_index(position)]
     }
 }
 
@@ -68,17 +116,66 @@ impl FromStr for Map {
             .lines()
             .map(|line| line.chars().collect::<Vec<_>>())
             .collect::<Vec<_>>();
+
+        if grid.len() < 3 {
+            return Err(err_msg(format!(
+                "Map has {} rows, expected at least 3",
+                grid.len()
+            )));
+        }
+        let row_len = grid[0].len();
+        if row_len < 3 {
+            return Err(err_msg(format!(
+                "Map is {} wide, expected at least 3",
+                row_len
+            )));
+        }
+        if let Some((y, row)) = grid
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.len() != row_len)
+        {
+            return Err(err_msg(format!(
+                "Row {} is {} wide, expected {}",
+                y,
+                row.len(),
+                row_len
+            )));
+        }
+
         let height = grid.len() as i64 - 2;
-        let width = grid[0].len() as i64 - 2;
+        let width = row_len as i64 - 2;
 
-        assert!(grid[0][1] == '.');
-        let start = Position { x: 0, y: -1 };
-        assert!(grid[height as usize + 1][width as usize] == '.');
+        let opening = |row: &[char], wall: &str| match row
+            .iter()
+            .positions(|&c| c != '#')
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            &[x] if x > 0 && x <= width as usize && row[x] == '.' => Ok(x as i64 - 1),
+            _ => Err(err_msg(format!(
+                "Expected a single opening in the {} wall",
+                wall
+            ))),
+        };
+        let start = Position {
+            x: opening(&grid[0], "top")?,
+            y: -1,
+        };
         let end = Position {
-            x: width - 1,
+            x: opening(&grid[height as usize + 1], "bottom")?,
             y: height,
         };
 
+        for (y, row) in grid[1..=height as usize].iter().enumerate() {
+            if row[0] != '#' || row[row_len - 1] != '#' {
+                return Err(err_msg(format!("Row {} isn't enclosed by walls", y + 1)));
+            }
+            if let Some(c) = row[1..row_len - 1].iter().find(|c| !".<>^v".contains(**c)) {
+                return Err(err_msg(format!("Invalid character {:?} in the valley", c)));
+            }
+        }
+
         let blizzards = array::from_fn(|d| {
             let direction = Direction::try_from(d).unwrap();
             let (outer_len, inner_len, outer_is_x) = match direction {
@@ -104,13 +201,17 @@ impl FromStr for Map {
                 .into_boxed_slice()
         });
 
-        Ok(Map {
+        let mut map = Map {
             blizzards,
             height,
             width,
             start,
             end,
-        })
+            occupied: Box::new([]),
+        };
+        map.occupied = map.find_occupied();
+
+        Ok(map)
     }
 }
 
@@ -164,20 +265,35 @@ impl<'a> a_star::State for State<'a> {
     }
 }
 
+fn travel(map: &Map, from: Position, to: Position, start_time: u64) -> Option<u64> {
+    let start = State {
+        map,
+        position: from,
+        target: to,
+        time: start_time,
+    };
+    a_star::solve(start)
+        .map(|(min_time, _)| start_time + min_time)
+        .ok()
+}
+
+/// Finds the time of the earliest arrival at `to`, having left `from` at time `t0`.
+///
+/// Either position may be any cell in the valley or one of the openings in the wall; `None` is
+/// returned for positions in or beyond the wall.
+pub fn route_between(map: &Map, from: Position, to: Position, t0: u64) -> Option<u64> {
+    if !map.is_open(from) || !map.is_open(to) {
+        return None;
+    }
+
+    travel(map, from, to, t0)
+}
+
 fn find_quickest_route(map: &Map, positions: &[Position]) -> Option<u64> {
     positions
         .iter()
         .zip(positions[1..].iter())
-        .try_fold(0, |time, (&position, &target)| {
-            let start = State {
-                map,
-                position,
-                target,
-                time,
-            };
-            a_star::solve(start).map(|(min_time, _)| time + min_time)
-        })
-        .ok()
+        .try_fold(0, |time, (&from, &to)| travel(map, from, to, time))
 }
 
 pub struct Solver {}
@@ -191,22 +307,144 @@ impl super::Solver for Solver {
 
     fn solve(map: Self::Problem) -> (Option<String>, Option<String>) {
         let part_one = find_quickest_route(&map, &[map.start, map.end])
-            .expect("Failed to solve part one")
-            .to_string();
+            .ok_or_else(|| err_msg("No route from start to end"));
 
         let part_two = find_quickest_route(&map, &[map.start, map.end, map.start, map.end])
-            .expect("Failed to solve part two")
-            .to_string();
-        (Some(part_one), Some(part_two))
+            .ok_or_else(|| err_msg("No route there, back and there again"));
+
+        (report(1, part_one), report(2, part_two))
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Map;
+    use super::{find_quickest_route, route_between, travel, Map};
     use crate::common::{Direction, Position};
+    use crate::parsers::positioned_chars;
+    use crate::Solver;
     use std::collections::HashSet;
 
+    const SAMPLE: &str = "#.######
+#>>.<^<#
+#.<..<<#
+#>v.><>#
+#<^v^^>#
+######.#
+";
+
+    #[test]
+    fn test_sample() {
+        let map = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(travel(&map, map.start, map.end, 0), Some(18));
+        assert_eq!(travel(&map, map.end, map.start, 18), Some(18 + 23));
+        assert_eq!(travel(&map, map.start, map.end, 18 + 23), Some(54));
+        assert_eq!(
+            find_quickest_route(&map, &[map.start, map.end, map.start, map.end]),
+            Some(54)
+        );
+        assert_eq!(
+            super::Solver::solve(map),
+            (Some("18".to_string()), Some("54".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_occupied_matches_blizzards() {
+        let map = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(map.occupied.len(), 12);
+
+        for time in 0..24 {
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    let has_blizzard = Direction::all().any(|direction| {
+                        let (row_or_col, check) = match direction {
+                            Direction::North | Direction::South => (x, y),
+                            Direction::East | Direction::West => (y, x),
+                        };
+                        map.blizzards_in_direction_at_time(direction, row_or_col, time)
+                            .any(|pos| pos == check)
+                    });
+                    assert_eq!(
+                        map.is_free_at_time(Position { x, y }, time),
+                        !has_blizzard,
+                        "({}, {}) at time {}",
+                        x,
+                        y,
+                        time
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_route_between() {
+        let map = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(route_between(&map, map.end(), map.start(), 18), Some(41));
+        assert_eq!(route_between(&map, map.end(), map.start(), 0), Some(17));
+
+        let corner = Position { x: 0, y: 0 };
+        let route = route_between(&map, corner, map.end(), 1).unwrap();
+        assert!(route > corner.manhattan_distance_to(&map.end()));
+        assert_eq!(route_between(&map, corner, corner, 5), Some(5));
+
+        let wall = Position { x: -1, y: 0 };
+        assert_eq!(route_between(&map, map.start(), wall, 0), None);
+        assert_eq!(route_between(&map, wall, map.end(), 0), None);
+    }
+
+    #[test]
+    fn test_malformed() {
+        let error = |data: &str| data.parse::<Map>().err().unwrap().to_string();
+
+        assert_eq!(error(""), "Map has 0 rows, expected at least 3");
+        assert_eq!(
+            error("#######\n#.....#\n#####.#\n"),
+            "Expected a single opening in the top wall"
+        );
+        assert_eq!(
+            error("#.#####\n#.....#\n#######\n"),
+            "Expected a single opening in the bottom wall"
+        );
+        assert_eq!(
+            error("#..####\n#.....#\n#####.#\n"),
+            "Expected a single opening in the top wall"
+        );
+        assert_eq!(
+            error("#.#####\n#.....\n#####.#\n"),
+            "Row 1 is 6 wide, expected 7"
+        );
+        assert_eq!(
+            error("#.#####\n#.....#\n.....>#\n#####.#\n"),
+            "Row 2 isn't enclosed by walls"
+        );
+        assert_eq!(
+            error("#.#####\n#..x..#\n#####.#\n"),
+            "Invalid character 'x' in the valley"
+        );
+        assert_eq!(
+            super::Solver::parse_input(SAMPLE.replace('>', "<"))
+                .map(|_| ())
+                .ok(),
+            Some(())
+        );
+    }
+
+    #[test]
+    fn test_openings() {
+        let map: Map = "#####.#
+#.....#
+#.....#
+#.#####
+"
+        .parse()
+        .unwrap();
+        assert_eq!(map.start(), Position { x: 4, y: -1 });
+        assert_eq!(map.end(), Position { x: 0, y: 2 });
+        assert_eq!(route_between(&map, map.start(), map.end(), 0), Some(7));
+        assert_eq!(route_between(&map, map.end(), map.start(), 0), Some(7));
+    }
+
     #[test]
     fn test_parse() {
         let map_string = r#"#.######
@@ -270,17 +508,8 @@ mod test {
 ######.#
 "#;
         let map: Map = map_string.parse().unwrap();
-        let free: HashSet<Position> = (-1..)
-            .zip(map_string.lines())
-            .flat_map(|(y, line)| {
-                (-1..).zip(line.chars()).filter_map(move |(x, c)| {
-                    if c == '.' {
-                        Some(Position { x, y })
-                    } else {
-                        None
-                    }
-                })
-            })
+        let free: HashSet<Position> = positioned_chars(map_string, -1, -1)
+            .filter_map(|(position, c)| if c == '.' { Some(position) } else { None })
             .collect();
 
         for y in -1..5 {
@@ -1,29 +1,13 @@
-use failure::{err_msg, Error};
+use crate::balanced::{from_balanced, to_balanced, Base};
+use failure::Error;
 use std::{
     fmt::Display,
-    iter::{self, Sum},
-    ops::AddAssign,
+    iter::Sum,
+    ops::{Add, AddAssign, Mul, Neg, Sub},
     str::FromStr,
 };
 
-fn from_snafu_digit(c: char) -> Result<i64, Error> {
-    match c {
-        '0'..='2' => Ok(c.to_digit(5).unwrap() as i64),
-        '-' => Ok(-1),
-        '=' => Ok(-2),
-        _ => Err(err_msg(format!("Invalid digit {}", c))),
-    }
-}
-
-fn to_snafu_digit(val: i64) -> Result<char, Error> {
-    match val {
-        0..=2 => Ok(char::from_digit(val as u32, 5).unwrap()),
-        -1 => Ok('-'),
-        -2 => Ok('='),
-        _ => Err(err_msg(format!("Invalid digit {}", val))),
-    }
-}
-
+/// A number written in balanced base 5, with digits `=`, `-`, `0`, `1` and `2`.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Snafu(i64);
 
@@ -37,34 +21,49 @@ impl FromStr for Snafu {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.chars()
-            .rev()
-            .zip(0..)
-            .map(|(c, pow)| from_snafu_digit(c).map(|d| d * 5_i64.pow(pow)))
-            .collect::<Result<Vec<_>, _>>()
-            .map(|ds| ds.iter().sum::<i64>().into())
+        from_balanced(s, Base::Quinary).map(Snafu)
     }
 }
 
 impl Display for Snafu {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut value = self.0;
-        let digits = iter::from_fn(|| {
-            if value == 0 {
-                None
-            } else {
-                let mut d = value % 5;
-                if d > 2 {
-                    d -= 5;
-                }
-                value -= d;
-                value /= 5;
-                Some(to_snafu_digit(d).unwrap())
-            }
-        })
-        .collect::<Vec<_>>();
-        let string: String = digits.iter().rev().collect();
-        write!(f, "{}", string)
+        write!(f, "{}", to_balanced(self.0, Base::Quinary))
+    }
+}
+
+pub fn to_decimal(s: &Snafu) -> i64 {
+    s.0
+}
+
+impl Add<Snafu> for Snafu {
+    type Output = Snafu;
+
+    fn add(self, rhs: Snafu) -> Self::Output {
+        Snafu(self.0 + rhs.0)
+    }
+}
+
+impl Sub<Snafu> for Snafu {
+    type Output = Snafu;
+
+    fn sub(self, rhs: Snafu) -> Self::Output {
+        Snafu(self.0 - rhs.0)
+    }
+}
+
+impl Mul<Snafu> for Snafu {
+    type Output = Snafu;
+
+    fn mul(self, rhs: Snafu) -> Self::Output {
+        Snafu(self.0 * rhs.0)
+    }
+}
+
+impl Neg for Snafu {
+    type Output = Snafu;
+
+    fn neg(self) -> Self::Output {
+        Snafu(-self.0)
     }
 }
 
@@ -97,17 +96,65 @@ impl super::Solver for Solver {
     }
 
     fn solve(fuel: Self::Problem) -> (Option<String>, Option<String>) {
-        let part_one = fuel.iter().sum::<Snafu>().to_string();
-        (Some(part_one), None)
+        let total = fuel.iter().sum::<Snafu>();
+        let part_one = total.to_string();
+        let part_two = to_decimal(&total).to_string();
+        (Some(part_one), Some(part_two))
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Snafu;
+    use super::{to_decimal, Snafu};
+    use crate::Solver;
+
+    const SAMPLE: &str = "1=-0-2
+12111
+2=0=
+21
+2=01
+111
+20012
+112
+1=-1=
+1-12
+12
+1=
+122
+";
 
     #[test]
     fn test_parse() {
         assert_eq!("1=-0-2".parse::<Snafu>().unwrap(), Snafu(1747))
     }
+
+    #[test]
+    fn test_round_trip() {
+        let snafu = "2=-1=0".parse::<Snafu>().unwrap();
+        assert_eq!(to_decimal(&snafu), 4890);
+        assert_eq!(Snafu::from(4890).to_string(), "2=-1=0");
+    }
+
+    #[test]
+    fn test_arithmetic() -> Result<(), failure::Error> {
+        assert_eq!(("1=".parse::<Snafu>()? * 2.into()).to_string(), "11");
+        assert_eq!(("1=".parse::<Snafu>()? + "2-".parse()?).to_string(), "22");
+        assert_eq!(("1=".parse::<Snafu>()? - "1=".parse()?).to_string(), "0");
+        assert_eq!(("1".parse::<Snafu>()? - "2".parse()?).to_string(), "-");
+        assert_eq!((-"2=-1=0".parse::<Snafu>()?).to_string(), "=21-20");
+        assert_eq!(
+            "=21-20".parse::<Snafu>()?,
+            Snafu::from(0) - "2=-1=0".parse()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample() {
+        let fuel = super::Solver::parse_input(SAMPLE.to_string()).unwrap();
+        assert_eq!(
+            super::Solver::solve(fuel),
+            (Some("2=-1=0".to_string()), Some("4890".to_string()))
+        );
+    }
 }
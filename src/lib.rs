@@ -5,33 +5,35 @@ use std::path::Path;
 use std::str::FromStr;
 
 mod a_star;
+pub mod balanced;
 mod common;
-mod day01;
-mod day02;
-mod day03;
-mod day04;
-mod day05;
-mod day06;
-mod day07;
-mod day08;
-mod day09;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day20;
-mod day21;
-mod day22;
-mod day23;
-mod day24;
-mod day25;
-mod parsers;
+pub mod day01;
+pub mod day02;
+pub mod day03;
+pub mod day04;
+pub mod day05;
+pub mod day06;
+pub mod day07;
+pub mod day08;
+pub mod day09;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
+pub mod day20;
+pub mod day21;
+pub mod day22;
+pub mod day23;
+pub mod day24;
+pub mod day25;
+pub mod parsers;
+mod render;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum Part {
@@ -58,6 +60,13 @@ pub trait Solver {
     fn solve(problem: Self::Problem) -> (Option<String>, Option<String>);
 }
 
+fn report<T: ToString>(part: usize, solution: Result<T, Error>) -> Option<String> {
+    solution
+        .map_err(|err| eprintln!("Failed to solve part {}: {}", part, err))
+        .ok()
+        .map(|solution| solution.to_string())
+}
+
 fn read_from_server(aoc: &mut Aoc) -> Result<String, Error> {
     aoc.get_input(false)
 }
@@ -6,6 +6,13 @@ use structopt::StructOpt;
 
 use aoc2022::{read_input, solve_day, Part};
 
+use memory::{format_size, CountingAllocator};
+
+mod memory;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator::new();
+
 #[derive(StructOpt, Debug)]
 struct Opt {
     day: Option<u32>,
@@ -13,9 +20,43 @@ struct Opt {
 
     #[structopt(long)]
     submit: Option<Part>,
+
+    /// Show which day is running when running all days
+    #[structopt(long)]
+    progress: bool,
+
+    /// Report the peak heap memory used by each day
+    #[structopt(long)]
+    memory: bool,
 }
 
-fn run_day(day: u32, input: Option<PathBuf>, submit: Option<Part>) -> Result<(), Error> {
+fn report_memory() {
+    println!("Peak memory {}", format_size(ALLOCATOR.peak()));
+}
+
+const NUM_DAYS: u32 = 25;
+
+// A single line on stderr showing which day is being fetched, which is cleared before the day is
+// solved so that it doesn't end up mixed in with the solutions or any errors.
+struct Progress {
+    shown: bool,
+}
+
+impl Progress {
+    fn show(&mut self, day: u32) {
+        eprint!("[{}/{}] Day {} ...", day, NUM_DAYS, day);
+        self.shown = true;
+    }
+
+    fn clear(&mut self) {
+        if self.shown {
+            eprint!("\r\x1b[K");
+            self.shown = false;
+        }
+    }
+}
+
+fn read_day(day: u32, input: Option<PathBuf>) -> Result<(Aoc, String), Error> {
     let mut aoc = Aoc::new()
         .parse_cli(false)
         .year(Some(2022))
@@ -25,6 +66,12 @@ fn run_day(day: u32, input: Option<PathBuf>, submit: Option<Part>) -> Result<(),
     let data = read_input(input, &mut aoc)
         .map_err(|err| failure::err_msg(format!("Failed to read input: {}", err)))?;
 
+    Ok((aoc, data))
+}
+
+fn run_day(day: u32, input: Option<PathBuf>, submit: Option<Part>) -> Result<(), Error> {
+    let (mut aoc, data) = read_day(day, input)?;
+
     solve_day(day, data, &mut aoc, submit)?;
 
     Ok(())
@@ -34,7 +81,11 @@ fn main() -> Result<(), Error> {
     let opt = Opt::from_args();
 
     if let Some(day) = opt.day {
+        ALLOCATOR.reset_peak();
         run_day(day, opt.input, opt.submit)?;
+        if opt.memory {
+            report_memory();
+        }
     } else {
         if opt.input.is_some() {
             return Err(err_msg("Can't provide input for all days"));
@@ -42,10 +93,21 @@ fn main() -> Result<(), Error> {
         if opt.submit.is_some() {
             return Err(err_msg("Can't submit solution for all days"));
         }
-        for day in 1..=25 {
-            println!("Day {}", day);
+        let mut progress = Progress { shown: false };
+        for day in 1..=NUM_DAYS {
+            ALLOCATOR.reset_peak();
             let start = Instant::now();
-            run_day(day, None, None)?;
+            if opt.progress {
+                progress.show(day);
+                let input = read_day(day, None);
+                progress.clear();
+                println!("Day {}", day);
+                let (mut aoc, data) = input?;
+                solve_day(day, data, &mut aoc, None)?;
+            } else {
+                println!("Day {}", day);
+                run_day(day, None, None)?;
+            }
             let elapsed = start.elapsed();
             if elapsed.as_secs() > 0 {
                 println!("Took {}.{:03}s", elapsed.as_secs(), elapsed.subsec_millis());
@@ -54,6 +116,9 @@ fn main() -> Result<(), Error> {
             } else {
                 println!("Took {}µs", elapsed.as_micros());
             }
+            if opt.memory {
+                report_memory();
+            }
             println!();
         }
     }
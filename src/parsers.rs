@@ -2,11 +2,13 @@ use nom::{
     bytes::complete::{tag, take_while1},
     character::complete::digit1,
     combinator::{map_res, opt, recognize},
-    sequence::pair,
+    sequence::{pair, tuple},
     IResult,
 };
 use std::str::FromStr;
 
+use crate::common::Position;
+
 pub fn unsigned<T: FromStr>(input: &str) -> IResult<&str, T> {
     map_res(take_while1(|c: char| c.is_ascii_digit()), |size: &str| {
         size.parse()
@@ -18,3 +20,60 @@ pub fn signed(input: &str) -> IResult<&str, i64> {
         val.parse()
     })(input)
 }
+
+pub fn float(input: &str) -> IResult<&str, f64> {
+    map_res(
+        recognize(tuple((opt(tag("-")), digit1, opt(pair(tag("."), digit1))))),
+        |val: &str| val.parse(),
+    )(input)
+}
+
+pub fn positioned_chars(
+    data: &str,
+    x_offset: i64,
+    y_offset: i64,
+) -> impl Iterator<Item = (Position, char)> + '_ {
+    (y_offset..).zip(data.lines()).flat_map(move |(y, line)| {
+        (x_offset..)
+            .zip(line.chars())
+            .map(move |(x, c)| (Position { x, y }, c))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{float, positioned_chars};
+    use crate::common::Position;
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn test_float() {
+        assert_eq!(float("3.14"), Ok(("", 3.14)));
+        assert_eq!(float("-0.5"), Ok(("", -0.5)));
+        assert_eq!(float("42"), Ok(("", 42.0)));
+        assert!(float("abc").is_err());
+    }
+
+    #[test]
+    fn test_positioned_chars() {
+        let data = "ab\ncd\n";
+        assert_eq!(
+            positioned_chars(data, 0, 0).collect::<Vec<_>>(),
+            vec![
+                (Position { x: 0, y: 0 }, 'a'),
+                (Position { x: 1, y: 0 }, 'b'),
+                (Position { x: 0, y: 1 }, 'c'),
+                (Position { x: 1, y: 1 }, 'd'),
+            ]
+        );
+        assert_eq!(
+            positioned_chars(data, -1, 2).collect::<Vec<_>>(),
+            vec![
+                (Position { x: -1, y: 2 }, 'a'),
+                (Position { x: 0, y: 2 }, 'b'),
+                (Position { x: -1, y: 3 }, 'c'),
+                (Position { x: 0, y: 3 }, 'd'),
+            ]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::iter::once;
+
+use crate::common::{Bounds, Position};
+
+/// Draws each position within the bounds as a character, with a line for each row.
+pub fn render_grid<F: Fn(Position) -> char>(bounds: &Bounds, cell: F) -> String {
+    let bounds = match bounds.non_empty() {
+        Some(bounds) => bounds,
+        None => return String::new(),
+    };
+
+    bounds
+        .iter_y()
+        .flat_map(|y| {
+            bounds
+                .iter_x()
+                .map(move |x| Position { x, y })
+                .map(&cell)
+                .chain(once('\n'))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::render_grid;
+    use crate::common::{Bounds, Position};
+
+    #[test]
+    fn test_render_grid() {
+        let bounds = Bounds::from([Position { x: -1, y: 2 }, Position { x: 2, y: 4 }]);
+        assert_eq!(
+            render_grid(&bounds, |position| if position.x == position.y - 3 {
+                '#'
+            } else {
+                '.'
+            }),
+            "#...\n.#..\n..#.\n"
+        );
+        assert_eq!(render_grid(&Bounds::EMPTY, |_| '#'), "");
+    }
+}